@@ -1,13 +1,21 @@
-use crate::pomodoro_core::PomodoroStates;
+use crate::pomodoro_core::{IntervalProgress, PomodoroStates};
 use std::sync::mpsc::Receiver;
 
 pub enum PomodoroChannel {
-    Update(u64),
-    Completed(PomodoroStates, u32),
+    Update(IntervalProgress),
+    Paused(IntervalProgress),
+    Completed(PomodoroStates, u32, Option<String>),
+    /// A new day began while waiting to proceed, with its count of pomodoros.
+    DayStarted(u32),
 }
 
 pub enum UIChannel {
     Proceed,
+    Pause,
+    Resume,
+    Skip,
+    Abort,
+    Task(Option<String>),
     Cancel,
 }
 
@@ -16,5 +24,5 @@ pub trait ConcSender<T> {
 }
 
 pub trait ConcReceiver<T> {
-    fn register_receiver(&mut self, receiver: Receiver<T>) -> ();
+    fn register_receiver(&mut self, receiver: Receiver<T>);
 }
@@ -0,0 +1,279 @@
+use crate::files::RecordFormat;
+use crate::pomodoro_core::PomodoroConfig;
+use crate::record::RecordMode;
+use crate::timing::DayBoundary;
+use chrono_tz::Tz;
+use serde::de::{Deserializer, Error, Unexpected, Visitor};
+use serde::Deserialize;
+use std::fmt;
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct AppConfig {
+    pub record: RecordConfig,
+    pub timer: TimerConfig,
+    pub ui: UIConfig,
+    pub notification: NotificationConfig,
+    pub goal: GoalConfig,
+    pub day: DayConfig,
+    // Flat keys from 0.2.0, superseded by the record section.
+    record_name: Option<String>,
+    record_location: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct RecordConfig {
+    pub enabled: bool,
+    pub name: String,
+    pub location: Option<String>,
+    pub mode: RecordMode,
+    pub format: RecordFormat,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct TimerConfig {
+    #[serde(deserialize_with = "positive_minutes")]
+    pub pomodoro_minutes: f32,
+    #[serde(deserialize_with = "positive_minutes")]
+    pub short_break_minutes: f32,
+    #[serde(deserialize_with = "positive_minutes")]
+    pub long_break_minutes: f32,
+    #[serde(deserialize_with = "positive_count")]
+    pub pomodoros_before_long_break: u32,
+    #[serde(deserialize_with = "positive_seconds")]
+    pub tick_seconds: f32,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct UIConfig {
+    pub colors: bool,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub bell: bool,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct GoalConfig {
+    #[serde(deserialize_with = "optional_positive_count")]
+    pub daily_pomodoros: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct DayConfig {
+    /// An IANA time zone such as "Europe/Berlin". None means local time.
+    #[serde(deserialize_with = "time_zone")]
+    pub time_zone: Option<Tz>,
+    #[serde(deserialize_with = "hour")]
+    pub starts_at_hour: u32,
+}
+
+impl AppConfig {
+    pub(crate) fn apply_legacy_keys(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let Some(name) = self.record_name.take() {
+            warnings.push("record_name is deprecated, use record.name instead".to_string());
+            self.record.name = name;
+        }
+        if let Some(location) = self.record_location.take() {
+            warnings.push("record_location is deprecated, use record.location instead".to_string());
+            self.record.location = Some(location);
+        }
+        warnings
+    }
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        RecordConfig {
+            enabled: true,
+            name: "pom-record".to_string(),
+            location: None,
+            mode: RecordMode::default(),
+            format: RecordFormat::default(),
+        }
+    }
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        let defaults = PomodoroConfig::default();
+        TimerConfig {
+            pomodoro_minutes: defaults.pomodoro_time_in_mins,
+            short_break_minutes: defaults.short_break_time_in_mins,
+            long_break_minutes: defaults.long_break_time_in_mins,
+            pomodoros_before_long_break: defaults.pomodoros_before_long_break,
+            tick_seconds: defaults.tick_in_secs,
+        }
+    }
+}
+
+impl From<&TimerConfig> for PomodoroConfig {
+    fn from(timer: &TimerConfig) -> Self {
+        PomodoroConfig {
+            pomodoro_time_in_mins: timer.pomodoro_minutes,
+            short_break_time_in_mins: timer.short_break_minutes,
+            long_break_time_in_mins: timer.long_break_minutes,
+            pomodoros_before_long_break: timer.pomodoros_before_long_break,
+            tick_in_secs: timer.tick_seconds,
+        }
+    }
+}
+
+impl From<&DayConfig> for DayBoundary {
+    fn from(day: &DayConfig) -> Self {
+        DayBoundary::new(day.time_zone, day.starts_at_hour)
+    }
+}
+
+impl Default for UIConfig {
+    fn default() -> Self {
+        UIConfig { colors: true }
+    }
+}
+
+fn positive_minutes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    deserializer
+        .deserialize_f64(NumberVisitor {
+            expecting: "a number of minutes from one second to a day",
+            is_valid: PomodoroConfig::is_valid_minutes,
+        })
+        .map(|minutes| minutes as f32)
+}
+
+fn positive_seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    deserializer
+        .deserialize_f64(NumberVisitor {
+            expecting: "a number of seconds from 0.1 to 60",
+            is_valid: PomodoroConfig::is_valid_tick,
+        })
+        .map(|seconds| seconds as f32)
+}
+
+fn positive_count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    deserializer.deserialize_u64(PositiveCountVisitor)
+}
+
+fn optional_positive_count<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u32>, D::Error> {
+    positive_count(deserializer).map(Some)
+}
+
+fn time_zone<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Tz>, D::Error> {
+    deserializer.deserialize_str(TimeZoneVisitor).map(Some)
+}
+
+fn hour<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    deserializer.deserialize_u64(HourVisitor)
+}
+
+// The checks run inside the visitors so that the error is reported with the
+// full key path and line of the offending value.
+struct NumberVisitor {
+    expecting: &'static str,
+    is_valid: fn(f64) -> bool,
+}
+
+impl<'de> Visitor<'de> for NumberVisitor {
+    type Value = f64;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.expecting)
+    }
+
+    fn visit_f64<E: Error>(self, value: f64) -> Result<f64, E> {
+        if (self.is_valid)(value) {
+            Ok(value)
+        } else {
+            Err(E::invalid_value(Unexpected::Float(value), &self))
+        }
+    }
+
+    fn visit_i64<E: Error>(self, value: i64) -> Result<f64, E> {
+        if (self.is_valid)(value as f64) {
+            Ok(value as f64)
+        } else {
+            Err(E::invalid_value(Unexpected::Signed(value), &self))
+        }
+    }
+
+    fn visit_u64<E: Error>(self, value: u64) -> Result<f64, E> {
+        self.visit_f64(value as f64)
+    }
+}
+
+struct PositiveCountVisitor;
+
+impl<'de> Visitor<'de> for PositiveCountVisitor {
+    type Value = u32;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a whole number above zero")
+    }
+
+    fn visit_i64<E: Error>(self, value: i64) -> Result<u32, E> {
+        if value > 0 && value <= i64::from(u32::MAX) {
+            Ok(value as u32)
+        } else {
+            Err(E::invalid_value(Unexpected::Signed(value), &self))
+        }
+    }
+
+    fn visit_u64<E: Error>(self, value: u64) -> Result<u32, E> {
+        if value > 0 && value <= u64::from(u32::MAX) {
+            Ok(value as u32)
+        } else {
+            Err(E::invalid_value(Unexpected::Unsigned(value), &self))
+        }
+    }
+}
+
+struct TimeZoneVisitor;
+
+impl<'de> Visitor<'de> for TimeZoneVisitor {
+    type Value = Tz;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a time zone such as \"Europe/Berlin\"")
+    }
+
+    fn visit_str<E: Error>(self, value: &str) -> Result<Tz, E> {
+        value
+            .parse()
+            .map_err(|_| E::invalid_value(Unexpected::Str(value), &self))
+    }
+}
+
+struct HourVisitor;
+
+impl<'de> Visitor<'de> for HourVisitor {
+    type Value = u32;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an hour from 0 to 23")
+    }
+
+    fn visit_i64<E: Error>(self, value: i64) -> Result<u32, E> {
+        if (0..24).contains(&value) {
+            Ok(value as u32)
+        } else {
+            Err(E::invalid_value(Unexpected::Signed(value), &self))
+        }
+    }
+
+    fn visit_u64<E: Error>(self, value: u64) -> Result<u32, E> {
+        if value < 24 {
+            Ok(value as u32)
+        } else {
+            Err(E::invalid_value(Unexpected::Unsigned(value), &self))
+        }
+    }
+}
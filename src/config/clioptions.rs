@@ -0,0 +1,370 @@
+use crate::config::AppConfig;
+use crate::pomodoro_core::PomodoroConfig;
+use crate::stats::MAX_HISTOGRAM_DAYS;
+use clap::{App, Arg, SubCommand};
+use std::ffi::OsString;
+use std::path::Path;
+
+#[derive(Debug, Default, PartialEq)]
+pub enum CliCommand {
+    #[default]
+    Run,
+    Stats {
+        min_pomodoros: u32,
+        days: u32,
+    },
+    /// Writes the daily totals in the summary format to a new CSV file.
+    Export {
+        location: String,
+        name: String,
+    },
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct CliOptions {
+    pub command: CliCommand,
+    pub config_path: Option<String>,
+    pub pomodoro_minutes: Option<f32>,
+    pub short_break_minutes: Option<f32>,
+    pub long_break_minutes: Option<f32>,
+    pub record_path: Option<String>,
+    pub no_record: bool,
+    pub task: Option<String>,
+    pub daily_goal: Option<u32>,
+}
+
+impl CliOptions {
+    /// Parses the given arguments, the first one being the binary name.
+    /// `--help`, `--version` and invalid arguments come back as a clap error
+    /// which prints the appropriate message via `exit()`.
+    pub fn from_args<I, T>(args: I) -> Result<CliOptions, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = CliOptions::app().get_matches_from_safe(args)?;
+        let command = match matches.subcommand() {
+            ("stats", Some(stats)) => CliCommand::Stats {
+                min_pomodoros: stats.value_of("min").map(parse_count).unwrap_or(1),
+                days: stats.value_of("days").map(parse_count).unwrap_or(14),
+            },
+            ("export", Some(export)) => {
+                let (location, name) = location_and_name(export.value_of("file").unwrap());
+                CliCommand::Export { location, name }
+            }
+            _ => CliCommand::Run,
+        };
+        Ok(CliOptions {
+            command,
+            config_path: matches.value_of("config").map(String::from),
+            pomodoro_minutes: matches.value_of("pomodoro").map(parse_minutes),
+            short_break_minutes: matches.value_of("short-break").map(parse_minutes),
+            long_break_minutes: matches.value_of("long-break").map(parse_minutes),
+            record_path: matches.value_of("record").map(String::from),
+            no_record: matches.is_present("no-record"),
+            task: matches.value_of("task").map(String::from),
+            daily_goal: matches.value_of("goal").map(parse_count),
+        })
+    }
+
+    /// Overrides the values read from the config file with the ones given on
+    /// the command line.
+    pub fn apply_to(&self, config: &mut AppConfig) {
+        if let Some(minutes) = self.pomodoro_minutes {
+            config.timer.pomodoro_minutes = minutes;
+        }
+        if let Some(minutes) = self.short_break_minutes {
+            config.timer.short_break_minutes = minutes;
+        }
+        if let Some(minutes) = self.long_break_minutes {
+            config.timer.long_break_minutes = minutes;
+        }
+        if let Some(record_path) = self.record_path.as_ref() {
+            let (location, name) = location_and_name(record_path);
+            config.record.location = Some(location);
+            if !name.is_empty() {
+                config.record.name = name;
+            }
+        }
+        if let Some(daily_goal) = self.daily_goal {
+            config.goal.daily_pomodoros = Some(daily_goal);
+        }
+        if self.no_record {
+            config.record.enabled = false;
+        }
+    }
+
+    fn app() -> App<'static, 'static> {
+        App::new("rusty-pomodoro")
+            .version(env!("CARGO_PKG_VERSION"))
+            .about("A pomodoro timer for the terminal which keeps a record of your pomodoros")
+            .arg(
+                Arg::with_name("config")
+                    .long("config")
+                    .value_name("PATH")
+                    .help("Path of the config file"),
+            )
+            .arg(
+                Arg::with_name("pomodoro")
+                    .long("pomodoro")
+                    .value_name("MINUTES")
+                    .validator(validate_minutes)
+                    .help("Length of a pomodoro"),
+            )
+            .arg(
+                Arg::with_name("short-break")
+                    .long("short-break")
+                    .value_name("MINUTES")
+                    .validator(validate_minutes)
+                    .help("Length of a short break"),
+            )
+            .arg(
+                Arg::with_name("long-break")
+                    .long("long-break")
+                    .value_name("MINUTES")
+                    .validator(validate_minutes)
+                    .help("Length of a long break"),
+            )
+            .arg(
+                Arg::with_name("record")
+                    .long("record")
+                    .value_name("PATH")
+                    .conflicts_with("no-record")
+                    .help("Path of the record file, e.g. ~/pomodoros/pom-record.csv"),
+            )
+            .arg(
+                Arg::with_name("no-record")
+                    .long("no-record")
+                    .help("Do not read or write a record file"),
+            )
+            .arg(
+                Arg::with_name("task")
+                    .long("task")
+                    .value_name("NAME")
+                    .help("Task to work on in the first pomodoro"),
+            )
+            .arg(
+                Arg::with_name("goal")
+                    .long("goal")
+                    .value_name("POMODOROS")
+                    .validator(validate_count)
+                    .help("Number of pomodoros to finish today"),
+            )
+            .subcommand(
+                SubCommand::with_name("stats")
+                    .about("Prints statistics over the record")
+                    .arg(
+                        Arg::with_name("min")
+                            .long("min")
+                            .value_name("POMODOROS")
+                            .validator(validate_count)
+                            .help("Pomodoros a day needed to keep a streak going [default: 1]"),
+                    )
+                    .arg(
+                        Arg::with_name("days")
+                            .long("days")
+                            .value_name("DAYS")
+                            .validator(validate_days)
+                            .help("Days shown in the histogram, at most 3660 [default: 14]"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("export")
+                    .about("Writes the number of pomodoros per day to a new CSV file")
+                    .arg(
+                        Arg::with_name("file")
+                            .value_name("FILE")
+                            .required(true)
+                            .help("Path of the CSV file, e.g. ~/pomodoros/summary.csv"),
+                    ),
+            )
+    }
+}
+
+// CsvFile takes the directory and the file name without the ".csv" suffix.
+fn location_and_name(path: &str) -> (String, String) {
+    let path = Path::new(path);
+    let location = match path.parent() {
+        Some(location) if !location.as_os_str().is_empty() => {
+            location.to_string_lossy().to_string()
+        }
+        _ => ".".to_string(),
+    };
+    let name = path
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    (location, name)
+}
+
+fn parse_minutes(value: &str) -> f32 {
+    value.parse::<f32>().unwrap_or_default()
+}
+
+fn parse_count(value: &str) -> u32 {
+    value.parse::<u32>().unwrap_or_default()
+}
+
+fn validate_count(value: String) -> Result<(), String> {
+    match value.parse::<u32>() {
+        Ok(count) if count > 0 => Ok(()),
+        _ => Err(format!("{} is not a whole number above zero", value)),
+    }
+}
+
+fn validate_days(value: String) -> Result<(), String> {
+    match value.parse::<u32>() {
+        Ok(days) if days > 0 && days <= MAX_HISTOGRAM_DAYS => Ok(()),
+        _ => Err(format!(
+            "{} is not a number of days from 1 to {}",
+            value, MAX_HISTOGRAM_DAYS
+        )),
+    }
+}
+
+fn validate_minutes(value: String) -> Result<(), String> {
+    match value.parse::<f64>() {
+        Ok(minutes) if PomodoroConfig::is_valid_minutes(minutes) => Ok(()),
+        _ => Err(format!(
+            "{} is not a number of minutes from one second to a day",
+            value
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{AppConfig, CliCommand, CliOptions};
+
+    fn options(args: &[&str]) -> CliOptions {
+        let mut all_args = vec!["rusty-pomodoro"];
+        all_args.extend_from_slice(args);
+        CliOptions::from_args(all_args).unwrap()
+    }
+
+    #[test]
+    fn no_arguments_change_nothing() {
+        let mut config = AppConfig::default();
+        options(&[]).apply_to(&mut config);
+        assert_eq!(config, AppConfig::default());
+    }
+
+    #[test]
+    fn durations_override_the_config() {
+        let mut config = AppConfig::default();
+        options(&[
+            "--pomodoro",
+            "50",
+            "--short-break",
+            "10",
+            "--long-break",
+            "0.5",
+        ])
+        .apply_to(&mut config);
+        assert_eq!(config.timer.pomodoro_minutes, 50_f32);
+        assert_eq!(config.timer.short_break_minutes, 10_f32);
+        assert_eq!(config.timer.long_break_minutes, 0.5_f32);
+    }
+
+    #[test]
+    fn record_path_is_split_into_location_and_name() {
+        let mut config = AppConfig::default();
+        options(&["--record", "/tmp/records/work.csv"]).apply_to(&mut config);
+        assert_eq!(config.record.location, Some("/tmp/records".to_string()));
+        assert_eq!(config.record.name, "work");
+        assert!(config.record.enabled);
+    }
+
+    #[test]
+    fn bare_record_file_name_is_in_the_current_directory() {
+        let mut config = AppConfig::default();
+        options(&["--record", "work.csv"]).apply_to(&mut config);
+        assert_eq!(config.record.location, Some(".".to_string()));
+        assert_eq!(config.record.name, "work");
+    }
+
+    #[test]
+    fn export_takes_the_target_file() {
+        assert_eq!(
+            options(&["export", "/tmp/summary.csv"]).command,
+            CliCommand::Export {
+                location: "/tmp".to_string(),
+                name: "summary".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn record_can_be_disabled() {
+        let mut config = AppConfig::default();
+        options(&["--no-record"]).apply_to(&mut config);
+        assert!(!config.record.enabled);
+    }
+
+    #[test]
+    fn config_path_is_read() {
+        assert_eq!(
+            options(&["--config", "/etc/rp.yml"]).config_path,
+            Some("/etc/rp.yml".to_string())
+        );
+    }
+
+    #[test]
+    fn task_is_read() {
+        assert_eq!(
+            options(&["--task", "TICKET-42"]).task,
+            Some("TICKET-42".to_string())
+        );
+        assert_eq!(options(&[]).task, None);
+    }
+
+    #[test]
+    fn daily_goal_overrides_the_config() {
+        let mut config = AppConfig::default();
+        options(&["--goal", "10"]).apply_to(&mut config);
+        assert_eq!(config.goal.daily_pomodoros, Some(10));
+        assert!(CliOptions::from_args(vec!["rusty-pomodoro", "--goal", "0"]).is_err());
+        assert!(CliOptions::from_args(vec!["rusty-pomodoro", "stats", "--days", "3661"]).is_err());
+        assert!(
+            CliOptions::from_args(vec!["rusty-pomodoro", "stats", "--days", "4294967295"]).is_err()
+        );
+        assert!(CliOptions::from_args(vec!["rusty-pomodoro", "stats", "--days", "3660"]).is_ok());
+    }
+
+    #[test]
+    fn running_the_timer_is_the_default_command() {
+        assert_eq!(options(&[]).command, CliCommand::Run);
+    }
+
+    #[test]
+    fn stats_command() {
+        assert_eq!(
+            options(&["stats"]).command,
+            CliCommand::Stats {
+                min_pomodoros: 1,
+                days: 14
+            }
+        );
+        let stats_options = options(&[
+            "--record", "work.csv", "stats", "--min", "6", "--days", "30",
+        ]);
+        assert_eq!(
+            stats_options.command,
+            CliCommand::Stats {
+                min_pomodoros: 6,
+                days: 30
+            }
+        );
+        assert_eq!(stats_options.record_path, Some("work.csv".to_string()));
+    }
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        assert!(CliOptions::from_args(vec!["rusty-pomodoro", "--pomodoro", "0"]).is_err());
+        assert!(CliOptions::from_args(vec!["rusty-pomodoro", "--long-break", "ten"]).is_err());
+        assert!(CliOptions::from_args(vec!["rusty-pomodoro", "--pomodoro", "inf"]).is_err());
+        assert!(CliOptions::from_args(vec!["rusty-pomodoro", "--pomodoro", "1e30"]).is_err());
+        assert!(CliOptions::from_args(vec!["rusty-pomodoro", "--short-break", "0.001"]).is_err());
+        assert!(CliOptions::from_args(vec!["rusty-pomodoro", "--long-break", "1440"]).is_ok());
+    }
+}
@@ -0,0 +1,131 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = "rusty-pomodoro";
+const CONFIG_FILE: &str = "config.yml";
+const LEGACY_CONFIG_FILE: &str = "rp-config.yml";
+
+/// Where the config file is looked up and where the record is kept by
+/// default, following the XDG base directory specification.
+pub struct ConfigLocations {
+    xdg_config_home: Option<PathBuf>,
+    xdg_data_home: Option<PathBuf>,
+    home: Option<PathBuf>,
+}
+
+impl ConfigLocations {
+    pub fn new(
+        xdg_config_home: Option<PathBuf>,
+        xdg_data_home: Option<PathBuf>,
+        home: Option<PathBuf>,
+    ) -> ConfigLocations {
+        ConfigLocations {
+            xdg_config_home,
+            xdg_data_home,
+            home,
+        }
+    }
+
+    pub fn from_env() -> ConfigLocations {
+        ConfigLocations::new(
+            absolute_path_from_env("XDG_CONFIG_HOME"),
+            absolute_path_from_env("XDG_DATA_HOME"),
+            dirs::home_dir(),
+        )
+    }
+
+    /// Config file candidates in the order they are looked up.
+    pub fn config_candidates(&self) -> Vec<PathBuf> {
+        let mut candidates = Vec::new();
+        if let Some(config_home) = self.xdg_config_home.as_ref() {
+            candidates.push(config_home.join(APP_DIR).join(CONFIG_FILE));
+        }
+        if let Some(home) = self.home.as_ref() {
+            candidates.push(home.join(".config").join(APP_DIR).join(CONFIG_FILE));
+        }
+        candidates.push(PathBuf::from(LEGACY_CONFIG_FILE));
+        candidates
+    }
+
+    pub fn find_config_file(&self) -> Option<PathBuf> {
+        self.config_candidates()
+            .into_iter()
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Default directory of the record, the current directory if neither
+    /// `$XDG_DATA_HOME` nor the home directory is known.
+    pub fn data_dir(&self) -> PathBuf {
+        if let Some(data_home) = self.xdg_data_home.as_ref() {
+            data_home.join(APP_DIR)
+        } else if let Some(home) = self.home.as_ref() {
+            home.join(".local").join("share").join(APP_DIR)
+        } else {
+            PathBuf::from(".")
+        }
+    }
+}
+
+// The XDG specification asks for relative paths in these variables to be
+// ignored.
+fn absolute_path_from_env(key: &str) -> Option<PathBuf> {
+    env::var_os(key)
+        .map(PathBuf::from)
+        .filter(|path| Path::is_absolute(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ConfigLocations;
+    use std::path::PathBuf;
+
+    #[test]
+    fn config_candidates_are_in_lookup_order() {
+        let locations = ConfigLocations::new(
+            Some(PathBuf::from("/xdg/config")),
+            None,
+            Some(PathBuf::from("/home/user")),
+        );
+        assert_eq!(
+            locations.config_candidates(),
+            vec![
+                PathBuf::from("/xdg/config/rusty-pomodoro/config.yml"),
+                PathBuf::from("/home/user/.config/rusty-pomodoro/config.yml"),
+                PathBuf::from("rp-config.yml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn config_candidates_without_any_directories() {
+        let locations = ConfigLocations::new(None, None, None);
+        assert_eq!(
+            locations.config_candidates(),
+            vec![PathBuf::from("rp-config.yml")]
+        );
+    }
+
+    #[test]
+    fn data_dir_prefers_xdg_data_home() {
+        let locations = ConfigLocations::new(
+            None,
+            Some(PathBuf::from("/xdg/data")),
+            Some(PathBuf::from("/home/user")),
+        );
+        assert_eq!(
+            locations.data_dir(),
+            PathBuf::from("/xdg/data/rusty-pomodoro")
+        );
+    }
+
+    #[test]
+    fn data_dir_falls_back_to_home_and_current_directory() {
+        let locations = ConfigLocations::new(None, None, Some(PathBuf::from("/home/user")));
+        assert_eq!(
+            locations.data_dir(),
+            PathBuf::from("/home/user/.local/share/rusty-pomodoro")
+        );
+        let locations = ConfigLocations::new(None, None, None);
+        assert_eq!(locations.data_dir(), PathBuf::from("."));
+    }
+}
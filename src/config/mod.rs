@@ -1,3 +1,11 @@
+mod appconfig;
+mod clioptions;
+mod configlocations;
 mod yamlconfig;
 
+pub use appconfig::{
+    AppConfig, DayConfig, GoalConfig, NotificationConfig, RecordConfig, TimerConfig, UIConfig,
+};
+pub use clioptions::{CliCommand, CliOptions};
+pub use configlocations::ConfigLocations;
 pub use yamlconfig::YamlConfig;
@@ -1,30 +1,199 @@
-use std::collections::BTreeMap;
+use crate::config::AppConfig;
+use serde_yaml::Value;
 use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
 
 pub struct YamlConfig<'a> {
     file_name: &'a str,
-    config_values: BTreeMap<String, String>,
+    config: AppConfig,
+    warnings: Vec<String>,
 }
 
 impl<'a> YamlConfig<'a> {
-    pub fn new(file_name: &str) -> YamlConfig {
+    pub fn new(file_name: &str) -> YamlConfig<'_> {
         YamlConfig {
             file_name,
-            config_values: BTreeMap::new(),
+            config: AppConfig::default(),
+            warnings: Vec::new(),
         }
     }
 
-    pub fn parse(&mut self) -> Result<(), Box<Error>> {
-        let file = std::fs::File::open(self.file_name)?;
-        self.config_values = serde_yaml::from_reader(file)?;
+    /// Reads the config file. A missing file keeps the defaults, an invalid
+    /// one is reported with the offending key and line.
+    pub fn parse(&mut self) -> Result<(), Box<dyn Error>> {
+        let contents = match fs::read_to_string(self.file_name) {
+            Ok(contents) => contents,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(format!("{}: {}", self.file_name, e).into()),
+        };
+        self.parse_str(&contents)
+    }
+
+    pub fn config(&self) -> &AppConfig {
+        &self.config
+    }
+
+    pub fn into_config(self) -> AppConfig {
+        self.config
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    fn parse_str(&mut self, contents: &str) -> Result<(), Box<dyn Error>> {
+        if contents.trim().is_empty() {
+            return Ok(());
+        }
+        let mut config: AppConfig =
+            serde_yaml::from_str(contents).map_err(|e| format!("{}: {}", self.file_name, e))?;
+        let mut warnings = Vec::new();
+        let value: Value = serde_yaml::from_str(contents)?;
+        let _: AppConfig = serde_ignored::deserialize(value, |path| {
+            warnings.push(format!("unknown key {} in {}", path, self.file_name));
+        })?;
+        warnings.append(&mut config.apply_legacy_keys());
+        self.config = config;
+        self.warnings = warnings;
         Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::YamlConfig;
+    use crate::files::RecordFormat;
+    use crate::record::RecordMode;
+
+    fn parsed(contents: &str) -> YamlConfig<'static> {
+        let mut config = YamlConfig::new("rp-config.yml");
+        config.parse_str(contents).unwrap();
+        config
+    }
+
+    #[test]
+    fn missing_values_fall_back_to_defaults() {
+        let config = parsed("timer:\n  pomodoro_minutes: 50\n");
+        assert_eq!(config.config().timer.pomodoro_minutes, 50_f32);
+        assert_eq!(config.config().timer.short_break_minutes, 5_f32);
+        assert_eq!(config.config().record.name, "pom-record");
+        assert_eq!(config.config().record.location, None);
+        assert!(config.config().ui.colors);
+        assert!(!config.config().notification.bell);
+        assert_eq!(config.config().goal.daily_pomodoros, None);
+        assert_eq!(config.config().day.time_zone, None);
+        assert_eq!(config.config().day.starts_at_hour, 0);
+        assert!(config.warnings().is_empty());
+    }
+
+    #[test]
+    fn reads_all_sections() {
+        let config = parsed(
+            "record:\n  name: my-record\n  location: /tmp\n  mode: append\n  format: sqlite\ntimer:\n  short_break_minutes: 10\n  long_break_minutes: 22.5\n  pomodoros_before_long_break: 3\nui:\n  colors: false\nnotification:\n  bell: true\ngoal:\n  daily_pomodoros: 10\nday:\n  time_zone: Europe/Berlin\n  starts_at_hour: 4\n",
+        );
+        assert_eq!(config.config().record.name, "my-record");
+        assert_eq!(config.config().record.location, Some("/tmp".to_string()));
+        assert_eq!(config.config().record.mode, RecordMode::Append);
+        assert_eq!(config.config().record.format, RecordFormat::Sqlite);
+        assert_eq!(config.config().timer.short_break_minutes, 10_f32);
+        assert_eq!(config.config().timer.long_break_minutes, 22.5_f32);
+        assert_eq!(config.config().timer.pomodoros_before_long_break, 3);
+        assert!(!config.config().ui.colors);
+        assert!(config.config().notification.bell);
+        assert_eq!(config.config().goal.daily_pomodoros, Some(10));
+        assert_eq!(
+            config.config().day.time_zone,
+            Some(chrono_tz::Europe::Berlin)
+        );
+        assert_eq!(config.config().day.starts_at_hour, 4);
+    }
+
+    #[test]
+    fn legacy_record_keys_are_still_read() {
+        let config = parsed("record_name: old-record\nrecord_location: /tmp\n");
+        assert_eq!(config.config().record.name, "old-record");
+        assert_eq!(config.config().record.location, Some("/tmp".to_string()));
+        assert_eq!(config.warnings().len(), 2);
+    }
+
+    #[test]
+    fn unknown_keys_are_warned_about() {
+        let config = parsed("timer:\n  pomodoro_minuts: 50\ncolour: blue\n");
+        assert_eq!(
+            config.warnings(),
+            &[
+                "unknown key timer.pomodoro_minuts in rp-config.yml".to_string(),
+                "unknown key colour in rp-config.yml".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_values_report_key_and_line() {
+        let mut config = YamlConfig::new("rp-config.yml");
+        let error = config
+            .parse_str("record:\n  name: my-record\ntimer:\n  pomodoro_minutes: 0\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("timer.pomodoro_minutes"), "{}", error);
+        assert!(error.contains("line 4"), "{}", error);
+    }
+
+    #[test]
+    fn endless_and_too_short_intervals_are_errors() {
+        let mut config = YamlConfig::new("rp-config.yml");
+        for minutes in &[".inf", "1e30", "1441", "0.001", ".nan"] {
+            let error = config
+                .parse_str(&format!("timer:\n  pomodoro_minutes: {}\n", minutes))
+                .unwrap_err()
+                .to_string();
+            assert!(error.contains("timer.pomodoro_minutes"), "{}", error);
+        }
+        assert!(config
+            .parse_str("timer:\n  long_break_minutes: 1440\n")
+            .is_ok());
+    }
+
+    #[test]
+    fn ticks_are_limited_to_a_tenth_of_a_second_up_to_a_minute() {
+        let mut config = YamlConfig::new("rp-config.yml");
+        for seconds in &["0.1", "60"] {
+            assert!(config
+                .parse_str(&format!("timer:\n  tick_seconds: {}\n", seconds))
+                .is_ok());
+        }
+        for seconds in &["0.09", "1e-12", "60.5", ".inf", ".nan"] {
+            let error = config
+                .parse_str(&format!("timer:\n  tick_seconds: {}\n", seconds))
+                .unwrap_err()
+                .to_string();
+            assert!(error.contains("timer.tick_seconds"), "{}", error);
+        }
+    }
 
-    pub fn record_filename(&self) -> Option<&String> {
-        self.config_values.get(&"record_name".to_string())
+    #[test]
+    fn unknown_time_zones_and_hours_past_the_day_are_errors() {
+        let mut config = YamlConfig::new("rp-config.yml");
+        let error = config
+            .parse_str("day:\n  time_zone: Mars/Olympus\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("day.time_zone"), "{}", error);
+        let error = config
+            .parse_str("day:\n  starts_at_hour: 24\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("day.starts_at_hour"), "{}", error);
     }
 
-    pub fn record_location(&self) -> Option<&String> {
-        self.config_values.get(&"record_location".to_string())
+    #[test]
+    fn wrongly_typed_values_are_errors() {
+        let mut config = YamlConfig::new("rp-config.yml");
+        let error = config
+            .parse_str("ui:\n  colors: maybe\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("ui.colors"), "{}", error);
     }
 }
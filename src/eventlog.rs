@@ -0,0 +1,162 @@
+use crate::files::{RecordFile, RecordWriter, Schema};
+use crate::observers::Observer;
+use crate::pomodoro_core::IntervalEvent;
+use std::error::Error;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+/// Keeps one line per finished interval, next to the daily summary kept by
+/// `Record`.
+pub struct EventLog {
+    log_file: Arc<Mutex<dyn RecordFile>>,
+    writer: RecordWriter,
+}
+
+impl EventLog {
+    pub fn new(log_file: Arc<Mutex<dyn RecordFile>>) -> EventLog {
+        EventLog {
+            writer: RecordWriter::new(log_file.clone(), "the event log"),
+            log_file,
+        }
+    }
+
+    pub fn initialize(&self) -> Result<(), Box<dyn Error>> {
+        self.log_file
+            .lock()
+            .unwrap()
+            .open_or_create(&EventLog::schema())
+    }
+
+    pub fn schema() -> Schema {
+        Schema::new(
+            "event-log",
+            vec![vec![
+                "State",
+                "Start",
+                "End",
+                "Planned duration",
+                "Actual duration",
+                "Outcome",
+                "Task",
+            ]],
+        )
+    }
+
+    /// Failed writes are sent here instead of being printed.
+    pub fn set_error_sender(&self, errors: Sender<String>) {
+        self.writer.set_error_sender(errors);
+    }
+
+    /// Waits until every line queued so far has reached the file.
+    pub fn flush(&self) {
+        self.writer.flush();
+    }
+
+    fn construct_content_vec(&self, event: &IntervalEvent) -> Vec<String> {
+        vec![
+            event.state.to_string(),
+            event.started_at.to_rfc3339(),
+            event.ended_at.to_rfc3339(),
+            event.planned_secs.to_string(),
+            event.actual_secs().to_string(),
+            event.outcome.to_string(),
+            event.task.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl Observer for EventLog {
+    fn callback(&self, event: IntervalEvent) {
+        self.writer.append(self.construct_content_vec(&event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eventlog::EventLog;
+    use crate::files::nullfile::NullFile;
+    use crate::files::CsvFile;
+    use crate::observers::Observer;
+    use crate::pomodoro_core::{IntervalEvent, IntervalOutcome, PomodoroStates};
+    use chrono::prelude::*;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn one_line_per_interval() {
+        let log = EventLog::new(Arc::new(Mutex::new(NullFile::new(
+            true,
+            String::from("1970-01-01"),
+        ))));
+        let event = IntervalEvent {
+            state: PomodoroStates::ShortBreak,
+            next_state: PomodoroStates::Pomodoro,
+            finished_pomodoros: 2,
+            outcome: IntervalOutcome::Skipped,
+            started_at: Utc.ymd(2019, 7, 20).and_hms(10, 0, 0),
+            ended_at: Utc.ymd(2019, 7, 20).and_hms(10, 2, 30),
+            planned_secs: 300,
+            paused: Duration::from_secs(0),
+            task: Some("TICKET-42".to_string()),
+        };
+        assert_eq!(
+            log.construct_content_vec(&event),
+            vec![
+                "short break",
+                "2019-07-20T10:00:00+00:00",
+                "2019-07-20T10:02:30+00:00",
+                "300",
+                "150",
+                "skipped",
+                "TICKET-42",
+            ]
+        );
+    }
+
+    #[test]
+    fn paused_time_is_left_out_of_the_actual_duration() {
+        let log = EventLog::new(Arc::new(Mutex::new(NullFile::new(
+            true,
+            String::from("1970-01-01"),
+        ))));
+        let event = IntervalEvent {
+            state: PomodoroStates::Pomodoro,
+            next_state: PomodoroStates::ShortBreak,
+            finished_pomodoros: 1,
+            outcome: IntervalOutcome::Completed,
+            started_at: Utc.ymd(2019, 7, 20).and_hms(10, 0, 0),
+            ended_at: Utc.ymd(2019, 7, 20).and_hms(10, 35, 0),
+            planned_secs: 1500,
+            paused: Duration::from_secs(600),
+            task: None,
+        };
+        assert_eq!(log.construct_content_vec(&event)[4], "1500");
+    }
+
+    #[test]
+    fn failed_writes_are_sent_to_the_error_channel() {
+        let log = EventLog::new(Arc::new(Mutex::new(CsvFile::new(
+            "/nonexistent/directory".to_string(),
+            "log".to_string(),
+        ))));
+        let (errors, reported) = channel();
+        log.set_error_sender(errors);
+        log.callback(IntervalEvent {
+            state: PomodoroStates::Pomodoro,
+            next_state: PomodoroStates::ShortBreak,
+            finished_pomodoros: 1,
+            outcome: IntervalOutcome::Completed,
+            started_at: Utc.ymd(2019, 7, 20).and_hms(10, 0, 0),
+            ended_at: Utc.ymd(2019, 7, 20).and_hms(10, 25, 0),
+            planned_secs: 1500,
+            paused: Duration::from_secs(0),
+            task: None,
+        });
+        log.flush();
+        assert!(reported
+            .try_recv()
+            .unwrap()
+            .starts_with("Could not write to the event log"));
+    }
+}
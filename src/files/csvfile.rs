@@ -1,7 +1,11 @@
+use crate::files::csvrows::{format_row, parse_rows, CsvRow};
 use crate::files::recordfile::RecordFile;
+use crate::files::schema::Schema;
+use crate::files::textfile;
 use std::error::Error;
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
 
 pub struct CsvFile {
     filename: String,
@@ -14,121 +18,173 @@ impl CsvFile {
         }
     }
 
-    fn append_new_line(&self, contents: &[String]) -> Result<(), Box<Error>> {
-        let mut write_file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.filename)?;
-        let write_string = self.create_csv_line_from_vec(contents);
-        writeln!(write_file, "{}", write_string)?;
+    fn append_new_line(&self, contents: &[String]) -> Result<(), Box<dyn Error>> {
+        textfile::append_line(&self.filename, &format_row(contents))
+    }
+
+    fn read_rows(&self) -> Result<Vec<CsvRow>, Box<dyn Error>> {
+        Ok(parse_rows(&fs::read_to_string(&self.filename)?))
+    }
+
+    fn create(&self, schema: &Schema) -> Result<(), Box<dyn Error>> {
+        if let Some(directory) = Path::new(&self.filename).parent() {
+            // The default record location may not exist on a fresh system.
+            fs::create_dir_all(directory)?;
+        }
+        let mut file = File::create(&self.filename)?;
+        writeln!(file, "{}", format_row(&schema.headers()))?;
+        println!("Created record file: {}", &self.filename);
         Ok(())
     }
 
-    fn create_csv_line_from_vec(&self, vec: &[String]) -> String {
-        let mut write_string = String::new();
-        for (i, content) in vec.iter().enumerate() {
-            if i < &vec.len() - 1 {
-                write_string.push_str(content);
-                write_string.push_str(",");
-            } else {
-                write_string.push_str(content);
+    // The version of a file is told by its headers. Files of an older version
+    // are rewritten with the current headers and a copy of the old file is
+    // kept as "<name>.csv.v<version>.bak".
+    fn migrate(&self, schema: &Schema) -> Result<(), Box<dyn Error>> {
+        let rows = self.read_rows()?;
+        let headers = match rows.first() {
+            Some(row) => row.fields.clone().map_err(|e| {
+                format!(
+                    "the headers of {} cannot be read on line {}: {}",
+                    self.filename, row.line, e
+                )
+            })?,
+            None => {
+                return textfile::replace_lines(&self.filename, &[format_row(&schema.headers())])
             }
+        };
+        if schema.is_newer(&headers) {
+            return Err(format!(
+                "{} was written by a newer version of the app",
+                self.filename
+            )
+            .into());
+        }
+        let version = schema.version_of(&headers).ok_or_else(|| {
+            format!(
+                "{} has unknown headers: {}",
+                self.filename,
+                format_row(&headers)
+            )
+        })?;
+        if version == schema.current_version() {
+            return Ok(());
+        }
+        fs::copy(
+            &self.filename,
+            textfile::sibling_path(&self.filename, &format!("v{}.bak", version)),
+        )?;
+        let mut lines = vec![format_row(&schema.headers())];
+        for row in rows.into_iter().skip(1) {
+            lines.push(match row.fields {
+                Ok(fields) => format_row(&schema.migrate_row(&headers, &fields)),
+                Err(_) => row.raw,
+            });
+        }
+        textfile::replace_lines(&self.filename, &lines)?;
+        if version < schema.current_version() {
+            println!(
+                "Migrated {} from schema version {} to {}",
+                self.filename,
+                version,
+                schema.current_version()
+            );
+        }
+        Ok(())
+    }
+
+    // The last row below the headers, the number of rows including the
+    // headers and the column with the given header, or `fallback` if the
+    // headers do not name it.
+    fn last_row_with_column(
+        &self,
+        header: &str,
+        fallback: usize,
+    ) -> Option<(Vec<String>, usize, usize)> {
+        let mut rows = self.read_rows().ok()?;
+        if rows.len() < 2 {
+            return None;
         }
-        write_string
+        let column = column_of(&rows[0], header).unwrap_or(fallback);
+        let row_count = rows.len();
+        let last_row = rows.pop()?.fields.ok()?;
+        Some((last_row, row_count, column))
     }
 }
 
+fn column_of(header_row: &CsvRow, header: &str) -> Option<usize> {
+    header_row
+        .fields
+        .as_ref()
+        .ok()?
+        .iter()
+        .position(|name| name == header)
+}
+
 impl RecordFile for CsvFile {
-    fn open_or_create_with_headers(&self, headers: &[String]) {
-        let record = File::open(&self.filename);
-        match record {
-            Ok(_) => println!("Record file found"),
-            Err(_) => {
-                let created = File::create(&self.filename);
-                self.write_headers(headers)
-                    .expect("Headers could not be written");
-                match created {
-                    Ok(_) => println!("Created record file: {}", &self.filename),
-                    Err(_) => println!("Could not create the record file."),
-                }
-            }
+    fn open_or_create(&self, schema: &Schema) -> Result<(), Box<dyn Error>> {
+        if !Path::new(&self.filename).exists() {
+            return self.create(schema);
         }
+        self.locked(&mut || self.migrate(schema))
     }
 
-    fn write_headers(&self, headers: &[String]) -> Result<(), Box<Error>> {
+    fn write_headers(&self, headers: &[String]) -> Result<(), Box<dyn Error>> {
         self.append_new_line(headers)
     }
 
-    fn write_record_to_new_line(&self, record: Vec<String>) -> Result<(), Box<Error>> {
+    fn write_record_to_new_line(&self, record: Vec<String>) -> Result<(), Box<dyn Error>> {
         self.append_new_line(&record)
     }
 
+    /// `pos` counts the rows from 1, the headers included. Rows that span
+    /// several lines count once.
     fn overwrite_record_in_pos_with(
         &self,
         pos: usize,
         record: Vec<String>,
-    ) -> Result<(), Box<Error>> {
-        let mut record_file = Vec::new();
-        let read_file = File::open(&self.filename).expect("Could not open file.");
-        let reader = BufReader::new(read_file);
-        for line in reader.lines() {
-            let line = line.unwrap();
-            record_file.push(line);
+    ) -> Result<(), Box<dyn Error>> {
+        let mut lines: Vec<String> = self.read_rows()?.into_iter().map(|row| row.raw).collect();
+        match lines.get_mut(pos.wrapping_sub(1)) {
+            Some(line) => *line = format_row(&record),
+            None => return Err(format!("line {} is not in the record", pos).into()),
         }
-        let write_string = self.create_csv_line_from_vec(&record);
-        record_file[pos - 1] = write_string.to_string();
-        let mut write_file = File::create(&self.filename).expect("Could not open file.");
-        for entry in &record_file {
-            writeln!(write_file, "{}", entry)?;
+        textfile::replace_lines(&self.filename, &lines)
+    }
+
+    /// Malformed rows are skipped.
+    fn read_records(&self) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        Ok(self
+            .read_rows()?
+            .into_iter()
+            .skip(1)
+            .filter_map(|row| row.fields.ok())
+            .collect())
+    }
+
+    fn read_headers(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        match self.read_rows()?.into_iter().next() {
+            Some(CsvRow { line, fields, .. }) => fields
+                .map_err(|e| format!("the headers on line {} cannot be read: {}", line, e).into()),
+            None => Ok(Vec::new()),
         }
-        Ok(())
     }
 
     fn get_last_pomodoro_count(&self) -> Option<u32> {
-        let file = File::open(&self.filename);
-        match file {
-            Ok(file) => {
-                let mut line_position: usize = 0;
-                let mut last_line = String::new();
-                for line in BufReader::new(file).lines() {
-                    if line.is_ok() {
-                        last_line = line.unwrap();
-                        line_position += 1;
-                    }
-                }
-                if line_position > 1 {
-                    let split_line: Vec<&str> = last_line.split(',').collect();
-                    let finished_pomodoros_string = split_line.last().unwrap();
-                    let finished_pomodoros_int = finished_pomodoros_string.parse::<u32>().unwrap();
-                    return Some(finished_pomodoros_int);
-                }
-                None
-            }
-            Err(_) => None,
-        }
+        let (last_row, _, column) = self.last_row_with_column("Number of pomodoros", 1)?;
+        last_row.get(column)?.parse::<u32>().ok()
     }
 
     fn get_last_pomodoro_date_and_line_no(&self) -> Option<(String, usize)> {
-        let file = File::open(&self.filename);
-        match file {
-            Ok(file) => {
-                let mut line_position: usize = 0;
-                let mut last_line = String::new();
-                for line in BufReader::new(file).lines() {
-                    if line.is_ok() {
-                        last_line = line.unwrap();
-                        line_position += 1;
-                    }
-                }
-                if line_position > 1 {
-                    let split_line: Vec<&str> = last_line.split(',').collect();
-                    let last_pomodoro_date = split_line.first().unwrap().to_string();
-                    return Some((last_pomodoro_date, line_position));
-                }
-                None
-            }
-            Err(_) => None,
-        }
+        let (last_row, row_count, column) = self.last_row_with_column("Date", 0)?;
+        Some((last_row.get(column)?.clone(), row_count))
+    }
+
+    fn locked(
+        &self,
+        update: &mut dyn FnMut() -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        textfile::locked(&self.filename, update)
     }
 }
 
@@ -137,10 +193,12 @@ mod tests {
     extern crate remove_dir_all;
     use crate::files::csvfile::CsvFile;
     use crate::files::recordfile::RecordFile;
+    use crate::files::Schema;
     use remove_dir_all::*;
     use serial_test_derive::serial;
-    use std::fs::{DirBuilder, File};
+    use std::fs::{self, DirBuilder, File};
     use std::io::{BufRead, BufReader};
+    use std::path::Path;
     use std::thread;
     use std::time::Duration;
 
@@ -163,18 +221,16 @@ mod tests {
             Ok(file) => {
                 let mut line_position: u8 = 0;
                 let mut last_line: String = String::new();
-                for line in BufReader::new(file).lines() {
-                    if line.is_ok() {
-                        last_line = line.unwrap();
-                        line_position += 1;
-                    }
+                for line in BufReader::new(file).lines().map_while(Result::ok) {
+                    last_line = line;
+                    line_position += 1;
                 }
                 if line_position > 0 {
                     return Some((last_line.clone(), line_position));
                 }
-                return None;
+                None
             }
-            Err(_) => return None,
+            Err(_) => None,
         }
     }
 
@@ -182,6 +238,17 @@ mod tests {
         vec![String::from("Test"), String::from("Headers")]
     }
 
+    fn schema() -> Schema {
+        Schema::new(
+            "test",
+            vec![
+                vec!["Test"],
+                vec!["Test", "Headers"],
+                vec!["Test", "Headers", "More"],
+            ],
+        )
+    }
+
     fn content_vec(first: &str, sec: &str) -> Vec<String> {
         vec![String::from(first), String::from(sec)]
     }
@@ -191,11 +258,25 @@ mod tests {
     fn creates_a_file_if_none_exists() {
         setup();
         let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
-        let headers = header_vec();
-        file.open_or_create_with_headers(&headers);
-        let raw_file = File::open(FILEPATH_AND_NAME_WITH_SUFFIX);
-        assert!(raw_file.is_ok());
+        file.open_or_create(&schema())
+            .expect("Something went wrong");
+        let contents = fs::read_to_string(FILEPATH_AND_NAME_WITH_SUFFIX);
+        let headers = file.read_headers();
         clean_up();
+        assert_eq!(contents.unwrap(), "Test,Headers,More\n");
+        assert_eq!(headers.unwrap(), vec!["Test", "Headers", "More"]);
+    }
+
+    #[test]
+    #[serial]
+    fn creates_missing_directories() {
+        setup();
+        let file = CsvFile::new("./temp/nested".to_string(), FILENAME.to_string());
+        file.open_or_create(&schema())
+            .expect("Something went wrong");
+        let raw_file = File::open("./temp/nested/record.csv");
+        clean_up();
+        assert!(raw_file.is_ok());
     }
 
     #[test]
@@ -268,6 +349,42 @@ mod tests {
         }
     }
 
+    #[test]
+    #[serial]
+    fn overwriting_keeps_a_backup_of_the_previous_version() {
+        setup();
+        let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+        file.write_headers(&header_vec())
+            .expect("Something went wrong");
+        file.append_new_line(&content_vec("2019-01-01", "1"))
+            .expect("Something went wrong");
+        file.overwrite_record_in_pos_with(2, content_vec("2019-01-01", "2"))
+            .expect("Something went wrong");
+        let backup = fs::read_to_string("./temp/record.csv.bak");
+        let record = fs::read_to_string(FILEPATH_AND_NAME_WITH_SUFFIX);
+        let temp_file_left = Path::new("./temp/record.csv.tmp").exists();
+        clean_up();
+        assert_eq!(backup.unwrap(), "Test,Headers\n2019-01-01,1\n");
+        assert_eq!(record.unwrap(), "Test,Headers\n2019-01-01,2\n");
+        assert!(!temp_file_left);
+    }
+
+    #[test]
+    #[serial]
+    fn a_failed_overwrite_leaves_the_record_untouched() {
+        setup();
+        let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+        file.write_headers(&header_vec())
+            .expect("Something went wrong");
+        file.append_new_line(&content_vec("2019-01-01", "1"))
+            .expect("Something went wrong");
+        let result = file.overwrite_record_in_pos_with(3, content_vec("2019-01-02", "1"));
+        let record = fs::read_to_string(FILEPATH_AND_NAME_WITH_SUFFIX);
+        clean_up();
+        assert!(result.is_err());
+        assert_eq!(record.unwrap(), "Test,Headers\n2019-01-01,1\n");
+    }
+
     #[test]
     #[serial]
     fn get_last_pom_count() {
@@ -294,6 +411,46 @@ mod tests {
         }
     }
 
+    #[test]
+    #[serial]
+    fn get_last_pom_count_with_a_goal_column() {
+        setup();
+        let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+        file.write_headers(&header_vec())
+            .expect("Something went wrong");
+        file.append_new_line(&[
+            String::from("2019-01-01"),
+            String::from("3"),
+            String::from("10"),
+        ])
+        .expect("Something went wrong");
+        let count = file.get_last_pomodoro_count();
+        clean_up();
+        assert_eq!(count, Some(3));
+    }
+
+    #[test]
+    #[serial]
+    fn reading_all_records_skips_the_headers() {
+        setup();
+        let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+        let headers = header_vec();
+        file.write_headers(&headers).expect("Something went wrong");
+        file.append_new_line(&content_vec("2019-01-01", "1"))
+            .expect("Something went wrong");
+        file.append_new_line(&content_vec("2019-01-02", "3"))
+            .expect("Something went wrong");
+        let records = file.read_records();
+        clean_up();
+        assert_eq!(
+            records.unwrap(),
+            vec![
+                content_vec("2019-01-01", "1"),
+                content_vec("2019-01-02", "3")
+            ]
+        );
+    }
+
     #[test]
     #[serial]
     fn test_get_last_pomodoro_date_and_line_no() {
@@ -320,4 +477,148 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    #[serial]
+    fn fields_with_commas_and_quotes_are_kept_intact() {
+        setup();
+        let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+        file.write_headers(&header_vec())
+            .expect("Something went wrong");
+        let content = content_vec("write \"intro\", part 1", "multi\nline");
+        file.write_record_to_new_line(content.clone())
+            .expect("Something went wrong");
+        file.write_record_to_new_line(content_vec("2019-01-02", "1"))
+            .expect("Something went wrong");
+        file.overwrite_record_in_pos_with(3, content_vec("2019-01-02", "2"))
+            .expect("Something went wrong");
+        let records = file.read_records();
+        clean_up();
+        assert_eq!(
+            records.unwrap(),
+            vec![content, content_vec("2019-01-02", "2")]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn malformed_last_line_is_no_count() {
+        setup();
+        let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+        fs::write(
+            FILEPATH_AND_NAME_WITH_SUFFIX,
+            "Date,Number of pomodoros\n2019-01-01,1\n2019-01-02,x\"y\n",
+        )
+        .unwrap();
+        let count = file.get_last_pomodoro_count();
+        let records = file.read_records();
+        clean_up();
+        assert_eq!(count, None);
+        assert_eq!(records.unwrap(), vec![content_vec("2019-01-01", "1")]);
+    }
+
+    #[test]
+    #[serial]
+    fn columns_are_found_by_their_header() {
+        setup();
+        let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+        fs::write(
+            FILEPATH_AND_NAME_WITH_SUFFIX,
+            "Daily goal,Number of pomodoros,Date\n10,4,2019-01-01\n",
+        )
+        .unwrap();
+        let count = file.get_last_pomodoro_count();
+        let last_date = file.get_last_pomodoro_date_and_line_no();
+        let headers = file.read_headers();
+        clean_up();
+        assert_eq!(count, Some(4));
+        assert_eq!(last_date, Some(("2019-01-01".to_string(), 2)));
+        assert_eq!(headers.unwrap()[2], "Date");
+    }
+
+    #[test]
+    #[serial]
+    fn locked_updates_of_two_handles_do_not_get_lost() {
+        setup();
+        let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+        file.write_headers(&header_vec())
+            .expect("Something went wrong");
+        file.append_new_line(&content_vec("2019-01-01", "0"))
+            .expect("Something went wrong");
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                thread::spawn(|| {
+                    let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+                    for _ in 0..10 {
+                        file.locked(&mut || {
+                            let count = file.get_last_pomodoro_count().unwrap();
+                            file.overwrite_record_in_pos_with(
+                                2,
+                                content_vec("2019-01-01", &(count + 1).to_string()),
+                            )
+                        })
+                        .expect("Something went wrong");
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let count = file.get_last_pomodoro_count();
+        clean_up();
+        assert_eq!(count, Some(20));
+    }
+
+    #[test]
+    #[serial]
+    fn older_files_are_migrated_with_a_backup() {
+        setup();
+        let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+        fs::write(FILEPATH_AND_NAME_WITH_SUFFIX, "Test\n1\n2\n").unwrap();
+        let migrated = file.open_or_create(&schema());
+        let contents = fs::read_to_string(FILEPATH_AND_NAME_WITH_SUFFIX);
+        let backup = fs::read_to_string("./temp/record.csv.v1.bak");
+        let reopened = file.open_or_create(&schema());
+        clean_up();
+        assert!(migrated.is_ok());
+        assert_eq!(contents.unwrap(), "Test,Headers,More\n1,,\n2,,\n");
+        assert_eq!(backup.unwrap(), "Test\n1\n2\n");
+        assert!(reopened.is_ok());
+    }
+
+    #[test]
+    #[serial]
+    fn current_files_are_left_untouched() {
+        setup();
+        let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+        fs::write(FILEPATH_AND_NAME_WITH_SUFFIX, "Test,Headers,More\n1,2,3\n").unwrap();
+        let opened = file.open_or_create(&schema());
+        let contents = fs::read_to_string(FILEPATH_AND_NAME_WITH_SUFFIX);
+        let backed_up = Path::new("./temp/record.csv.v3.bak").exists();
+        clean_up();
+        assert!(opened.is_ok());
+        assert_eq!(contents.unwrap(), "Test,Headers,More\n1,2,3\n");
+        assert!(!backed_up);
+    }
+
+    #[test]
+    #[serial]
+    fn unknown_and_newer_files_are_refused() {
+        setup();
+        let file = CsvFile::new(FILEPATH.to_string(), FILENAME.to_string());
+        fs::write(FILEPATH_AND_NAME_WITH_SUFFIX, "Something,Else\n").unwrap();
+        let unknown = file.open_or_create(&schema());
+        fs::write(
+            FILEPATH_AND_NAME_WITH_SUFFIX,
+            "Test,Headers,More,Even more\n",
+        )
+        .unwrap();
+        let newer = file.open_or_create(&schema());
+        let contents = fs::read_to_string(FILEPATH_AND_NAME_WITH_SUFFIX);
+        clean_up();
+        assert!(unknown.unwrap_err().to_string().contains("unknown headers"));
+        assert!(newer.unwrap_err().to_string().contains("newer version"));
+        assert_eq!(contents.unwrap(), "Test,Headers,More,Even more\n");
+    }
 }
@@ -0,0 +1,239 @@
+/// One row of a CSV file as described in RFC 4180.
+#[derive(PartialEq, Clone, Debug)]
+pub struct CsvRow {
+    /// The line on which the row starts, counting from 1.
+    pub line: usize,
+    /// The row as it is in the file, without the line break at its end. Used
+    /// to write malformed rows back unchanged.
+    pub raw: String,
+    /// The fields of the row, or why they could not be read.
+    pub fields: Result<Vec<String>, String>,
+}
+
+/// Joins the fields with commas. Fields with a comma, a quote or a line break
+/// are put in quotes, quotes within them are doubled.
+pub fn format_row(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Splits the contents of a CSV file into rows. Line breaks may be LF or
+/// CRLF and empty lines are skipped. A malformed row does not stop the rows
+/// after it from being read.
+pub fn parse_rows(contents: &str) -> Vec<CsvRow> {
+    let mut rows = Vec::new();
+    let mut parser = RowParser::new(contents);
+    while let Some(row) = parser.next_row() {
+        if row.raw.is_empty() {
+            continue;
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+struct RowParser<'a> {
+    contents: &'a str,
+    position: usize,
+    line: usize,
+}
+
+impl<'a> RowParser<'a> {
+    fn new(contents: &'a str) -> RowParser<'a> {
+        RowParser {
+            contents,
+            position: 0,
+            line: 1,
+        }
+    }
+
+    fn next_row(&mut self) -> Option<CsvRow> {
+        if self.position >= self.contents.len() {
+            return None;
+        }
+        let start = self.position;
+        let line = self.line;
+        let fields = self.read_fields();
+        let end = self.position;
+        self.skip_line_break();
+        let raw = self.contents[start..end].to_string();
+        Some(CsvRow { line, raw, fields })
+    }
+
+    // Reads up to, but not including, the line break which ends the row. On
+    // an error the rest of the row is skipped.
+    fn read_fields(&mut self) -> Result<Vec<String>, String> {
+        let mut fields = Vec::new();
+        loop {
+            let field = if self.peek() == Some('"') {
+                self.read_quoted_field()
+            } else {
+                self.read_plain_field()
+            };
+            match field {
+                Ok(field) => fields.push(field),
+                Err(e) => {
+                    self.skip_to_line_break();
+                    return Err(e);
+                }
+            }
+            match self.peek() {
+                Some(',') => self.position += 1,
+                None | Some('\n') => return Ok(fields),
+                Some('\r') if self.rest().starts_with("\r\n") => return Ok(fields),
+                Some(c) => {
+                    self.skip_to_line_break();
+                    return Err(format!("unexpected {:?} after a quoted field", c));
+                }
+            }
+        }
+    }
+
+    fn read_plain_field(&mut self) -> Result<String, String> {
+        let rest = self.rest();
+        let length = rest.find([',', '\n']).unwrap_or(rest.len());
+        let field = rest[..length].trim_end_matches('\r');
+        if field.contains('"') {
+            return Err("quote in a field without quotes".to_string());
+        }
+        if field.contains('\r') {
+            return Err("carriage return in a field without quotes".to_string());
+        }
+        self.position += field.len();
+        Ok(field.to_string())
+    }
+
+    fn read_quoted_field(&mut self) -> Result<String, String> {
+        let line = self.line;
+        self.position += 1;
+        let mut field = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(format!("quote opened on line {} is not closed", line)),
+                Some('"') if self.rest().starts_with("\"\"") => {
+                    field.push('"');
+                    self.position += 2;
+                }
+                Some('"') => {
+                    self.position += 1;
+                    return Ok(field);
+                }
+                Some(c) => {
+                    if c == '\n' {
+                        self.line += 1;
+                    }
+                    field.push(c);
+                    self.position += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn skip_to_line_break(&mut self) {
+        let rest = self.rest();
+        let length = rest.find('\n').unwrap_or(rest.len());
+        self.position += rest[..length].trim_end_matches('\r').len();
+        if self.rest().starts_with('\r') && !self.rest().starts_with("\r\n") {
+            self.position += 1;
+        }
+    }
+
+    fn skip_line_break(&mut self) {
+        if self.rest().starts_with("\r\n") {
+            self.position += 2;
+        } else if self.rest().starts_with('\n') {
+            self.position += 1;
+        } else {
+            return;
+        }
+        self.line += 1;
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.contents[self.position..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::files::csvrows::{format_row, parse_rows};
+
+    fn fields(row: &[&str]) -> Vec<String> {
+        row.iter().map(|field| field.to_string()).collect()
+    }
+
+    fn parsed_fields(contents: &str) -> Vec<Result<Vec<String>, String>> {
+        parse_rows(contents)
+            .into_iter()
+            .map(|row| row.fields)
+            .collect()
+    }
+
+    #[test]
+    fn plain_fields_are_not_quoted() {
+        assert_eq!(
+            format_row(&fields(&["2019-07-20", "3", ""])),
+            "2019-07-20,3,"
+        );
+    }
+
+    #[test]
+    fn special_characters_are_quoted() {
+        assert_eq!(
+            format_row(&fields(&["a,b", "say \"hi\"", "two\nlines"])),
+            "\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\""
+        );
+    }
+
+    #[test]
+    fn formatted_rows_are_read_back() {
+        let row = fields(&["a,b", "say \"hi\"", "two\r\nlines", "", "plain"]);
+        let contents = format!("{}\n{}\n", format_row(&row), format_row(&row));
+        assert_eq!(parsed_fields(&contents), vec![Ok(row.clone()), Ok(row)]);
+    }
+
+    #[test]
+    fn crlf_and_empty_lines_are_accepted() {
+        assert_eq!(
+            parsed_fields("Date,Count\r\n\r\n2019-07-20,3\r\n"),
+            vec![
+                Ok(fields(&["Date", "Count"])),
+                Ok(fields(&["2019-07-20", "3"]))
+            ]
+        );
+    }
+
+    #[test]
+    fn rows_know_their_line_and_raw_text() {
+        let rows = parse_rows("a,\"multi\nline\"\nb,c\n");
+        assert_eq!(rows[0].line, 1);
+        assert_eq!(rows[0].raw, "a,\"multi\nline\"");
+        assert_eq!(rows[1].line, 3);
+        assert_eq!(rows[1].raw, "b,c");
+    }
+
+    #[test]
+    fn malformed_rows_do_not_stop_the_others() {
+        let rows = parse_rows("a,b\"c\n\"x\"y,z\nd,e\n\"open,f\n");
+        assert!(rows[0].fields.is_err());
+        assert_eq!(rows[0].raw, "a,b\"c");
+        assert!(rows[1].fields.is_err());
+        assert_eq!(rows[1].raw, "\"x\"y,z");
+        assert_eq!(rows[2].fields, Ok(fields(&["d", "e"])));
+        assert!(rows[3].fields.is_err());
+        assert_eq!(rows.len(), 4);
+    }
+}
@@ -0,0 +1,296 @@
+use crate::files::recordfile::RecordFile;
+use crate::files::schema::Schema;
+use crate::files::textfile;
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::Path;
+
+/// Keeps one JSON object per line, e.g.
+/// `{"date":"2019-07-20","number_of_pomodoros":3,"daily_goal":null}`. The
+/// keys are the headers of the schema in snake case, counts, goals and
+/// durations are numbers, timestamps RFC 3339 strings and empty fields are
+/// null. Objects written by an older version lack the newer keys, so the
+/// file needs no migration. Lines are only ever appended, a new count of a
+/// day included.
+pub struct JsonlFile {
+    filename: String,
+    schema: Schema,
+}
+
+impl JsonlFile {
+    pub fn new(filepath: String, filename: String, schema: Schema) -> JsonlFile {
+        JsonlFile {
+            filename: filepath + "/" + &filename + ".jsonl",
+            schema,
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.schema
+            .headers()
+            .iter()
+            .map(|header| key_of(header))
+            .collect()
+    }
+
+    fn format_object(&self, record: Vec<String>) -> Result<String, Box<dyn Error>> {
+        let mut fields = record.into_iter();
+        let mut object = Map::new();
+        for header in self.schema.headers() {
+            let value = match fields.next() {
+                Some(field) if field.is_empty() => Value::Null,
+                Some(field) if Schema::is_integer(&header) => Value::from(
+                    field
+                        .parse::<u64>()
+                        .map_err(|_| format!("{} is not a number: {}", header, field))?,
+                ),
+                Some(field) => Value::String(field),
+                None => Value::Null,
+            };
+            object.insert(key_of(&header), value);
+        }
+        Ok(Value::Object(object).to_string())
+    }
+
+    fn parse_object(&self, line: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let object = match serde_json::from_str(line)? {
+            Value::Object(object) => object,
+            _ => return Err("not a JSON object".into()),
+        };
+        Ok(self
+            .keys()
+            .iter()
+            .map(|key| match object.get(key) {
+                Some(Value::String(field)) => field.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(field) => field.to_string(),
+            })
+            .collect())
+    }
+
+    fn read_lines(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(fs::read_to_string(&self.filename)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.to_string())
+            .collect())
+    }
+
+    // Keys which the schema does not know were written by a newer version of
+    // the app, or by someone else.
+    fn check_keys(&self) -> Result<(), Box<dyn Error>> {
+        let keys = self.keys();
+        for (index, line) in self.read_lines()?.iter().enumerate() {
+            if let Ok(Value::Object(object)) = serde_json::from_str(line) {
+                if let Some(key) = object.keys().find(|key| !keys.contains(key)) {
+                    return Err(format!(
+                        "{} has the unknown key {} on line {}",
+                        self.filename,
+                        key,
+                        index + 1
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The last object and the number of lines, plus one as the position of a
+    // row counts the headers of a CSV file.
+    fn last_record(&self) -> Option<(Vec<String>, usize)> {
+        let lines = self.read_lines().ok()?;
+        let last_record = self.parse_object(lines.last()?).ok()?;
+        Some((last_record, lines.len() + 1))
+    }
+
+    fn field_of(&self, record: &[String], header: &str) -> Option<String> {
+        let column = self
+            .schema
+            .headers()
+            .iter()
+            .position(|name| name == header)?;
+        record.get(column).cloned()
+    }
+}
+
+impl RecordFile for JsonlFile {
+    fn open_or_create(&self, _schema: &Schema) -> Result<(), Box<dyn Error>> {
+        if Path::new(&self.filename).exists() {
+            return self.locked(&mut || self.check_keys());
+        }
+        if let Some(directory) = Path::new(&self.filename).parent() {
+            // The default record location may not exist on a fresh system.
+            fs::create_dir_all(directory)?;
+        }
+        File::create(&self.filename)?;
+        println!("Created record file: {}", &self.filename);
+        Ok(())
+    }
+
+    // Every object names its fields.
+    fn write_headers(&self, _headers: &[String]) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn write_record_to_new_line(&self, record: Vec<String>) -> Result<(), Box<dyn Error>> {
+        textfile::append_line(&self.filename, &self.format_object(record)?)
+    }
+
+    /// `pos` counts the rows from 1 as in a CSV file, so that the first line
+    /// is at 2.
+    fn overwrite_record_in_pos_with(
+        &self,
+        pos: usize,
+        record: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut lines = self.read_lines()?;
+        match pos.checked_sub(2).and_then(|index| lines.get_mut(index)) {
+            Some(line) => *line = self.format_object(record)?,
+            None => return Err(format!("line {} is not in the record", pos).into()),
+        }
+        textfile::replace_lines(&self.filename, &lines)
+    }
+
+    /// Malformed lines are skipped.
+    fn read_records(&self) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        Ok(self
+            .read_lines()?
+            .iter()
+            .filter_map(|line| self.parse_object(line).ok())
+            .collect())
+    }
+
+    fn read_headers(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self.schema.headers())
+    }
+
+    fn get_last_pomodoro_count(&self) -> Option<u32> {
+        let (last_record, _) = self.last_record()?;
+        self.field_of(&last_record, "Number of pomodoros")?
+            .parse()
+            .ok()
+    }
+
+    fn get_last_pomodoro_date_and_line_no(&self) -> Option<(String, usize)> {
+        let (last_record, position) = self.last_record()?;
+        Some((self.field_of(&last_record, "Date")?, position))
+    }
+
+    fn appends_counts(&self) -> bool {
+        true
+    }
+
+    fn locked(
+        &self,
+        update: &mut dyn FnMut() -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        textfile::locked(&self.filename, update)
+    }
+}
+
+// "Number of pomodoros" becomes "number_of_pomodoros".
+fn key_of(header: &str) -> String {
+    header.to_lowercase().replace(' ', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::files::jsonlfile::JsonlFile;
+    use crate::files::{RecordFile, Schema};
+    use std::fs;
+
+    fn schema() -> Schema {
+        Schema::new(
+            "record",
+            vec![
+                vec!["Date", "Number of pomodoros"],
+                vec!["Date", "Number of pomodoros", "Task"],
+            ],
+        )
+    }
+
+    fn row(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn temp_location(name: &str) -> String {
+        let location = std::env::temp_dir().join(format!("rp-{}-{}", name, std::process::id()));
+        location.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn objects_are_written_and_overwritten() {
+        let location = temp_location("jsonl");
+        let file = JsonlFile::new(location.clone(), "record".to_string(), schema());
+        file.open_or_create(&schema()).unwrap();
+        assert_eq!(file.get_last_pomodoro_date_and_line_no(), None);
+        file.write_record_to_new_line(row(&["2019-07-20", "1", "say \"hi\""]))
+            .unwrap();
+        file.write_record_to_new_line(row(&["2019-07-21", "1", ""]))
+            .unwrap();
+        file.overwrite_record_in_pos_with(3, row(&["2019-07-21", "2", ""]))
+            .unwrap();
+        assert!(file
+            .overwrite_record_in_pos_with(4, row(&["2019-07-22", "1", ""]))
+            .is_err());
+        assert!(file
+            .overwrite_record_in_pos_with(1, row(&["2019-07-22", "1", ""]))
+            .is_err());
+        assert!(file
+            .write_record_to_new_line(row(&["2019-07-22", "one", ""]))
+            .is_err());
+        let contents = fs::read_to_string(format!("{}/record.jsonl", location)).unwrap();
+        let records = file.read_records();
+        let last_count = file.get_last_pomodoro_count();
+        let last_date = file.get_last_pomodoro_date_and_line_no();
+        fs::remove_dir_all(&location).unwrap();
+        assert_eq!(
+            contents,
+            "{\"date\":\"2019-07-20\",\"number_of_pomodoros\":1,\"task\":\"say \\\"hi\\\"\"}\n\
+             {\"date\":\"2019-07-21\",\"number_of_pomodoros\":2,\"task\":null}\n"
+        );
+        assert_eq!(
+            records.unwrap(),
+            vec![
+                row(&["2019-07-20", "1", "say \"hi\""]),
+                row(&["2019-07-21", "2", ""])
+            ]
+        );
+        assert_eq!(last_count, Some(2));
+        assert_eq!(last_date, Some(("2019-07-21".to_string(), 3)));
+    }
+
+    #[test]
+    fn older_and_malformed_lines_are_read() {
+        let location = temp_location("jsonl-old");
+        fs::create_dir_all(&location).unwrap();
+        fs::write(
+            format!("{}/record.jsonl", location),
+            "{\"date\":\"2019-07-20\",\"number_of_pomodoros\":3}\nnot json\n",
+        )
+        .unwrap();
+        let file = JsonlFile::new(location.clone(), "record".to_string(), schema());
+        let opened = file.open_or_create(&schema());
+        let records = file.read_records();
+        fs::remove_dir_all(&location).unwrap();
+        assert!(opened.is_ok());
+        assert_eq!(records.unwrap(), vec![row(&["2019-07-20", "3", ""])]);
+    }
+
+    #[test]
+    fn unknown_keys_are_refused() {
+        let location = temp_location("jsonl-newer");
+        fs::create_dir_all(&location).unwrap();
+        fs::write(
+            format!("{}/record.jsonl", location),
+            "{\"date\":\"2019-07-20\",\"number_of_pomodoros\":\"3\",\"mood\":\"good\"}\n",
+        )
+        .unwrap();
+        let file = JsonlFile::new(location.clone(), "record".to_string(), schema());
+        let opened = file.open_or_create(&schema());
+        fs::remove_dir_all(&location).unwrap();
+        assert!(opened.is_err());
+    }
+}
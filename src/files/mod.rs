@@ -1,8 +1,22 @@
 mod csvfile;
+mod csvrows;
+mod jsonlfile;
 mod recordfile;
+mod recordformat;
+mod recordwriter;
+mod schema;
+mod sqlitefile;
+mod textfile;
+mod totals;
 
 pub use csvfile::CsvFile;
+pub use jsonlfile::JsonlFile;
 pub use recordfile::RecordFile;
+pub use recordformat::RecordFormat;
+pub use recordwriter::RecordWriter;
+pub use schema::Schema;
+pub use sqlitefile::SqliteFile;
+pub use totals::Totals;
 
 #[cfg(test)]
 pub mod nullfile;
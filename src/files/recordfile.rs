@@ -1,14 +1,39 @@
+use crate::files::{Schema, Totals};
 use std::error::Error;
 
 pub trait RecordFile: Send {
-    fn open_or_create_with_headers(&self, headers: &[String]);
-    fn write_headers(&self, headers: &[String]) -> Result<(), Box<Error>>;
-    fn write_record_to_new_line(&self, record: Vec<String>) -> Result<(), Box<Error>>;
+    /// Creates the file with the headers of the schema, or checks the headers
+    /// of an existing file and migrates it to the current version.
+    fn open_or_create(&self, schema: &Schema) -> Result<(), Box<dyn Error>>;
+    fn write_headers(&self, headers: &[String]) -> Result<(), Box<dyn Error>>;
+    fn write_record_to_new_line(&self, record: Vec<String>) -> Result<(), Box<dyn Error>>;
     fn overwrite_record_in_pos_with(
         &self,
         pos: usize,
         record: Vec<String>,
-    ) -> Result<(), Box<Error>>;
+    ) -> Result<(), Box<dyn Error>>;
+    fn read_records(&self) -> Result<Vec<Vec<String>>, Box<dyn Error>>;
+    fn read_headers(&self) -> Result<Vec<String>, Box<dyn Error>>;
     fn get_last_pomodoro_count(&self) -> Option<u32>;
     fn get_last_pomodoro_date_and_line_no(&self) -> Option<(String, usize)>;
+    /// Whether a new count of a day goes to a new row instead of overwriting
+    /// the day's row, so that the file is only ever appended to.
+    fn appends_counts(&self) -> bool {
+        false
+    }
+    /// Runs `update` while other instances of the app cannot write to the
+    /// file, so that a read and the write depending on it are not torn apart.
+    fn locked(
+        &self,
+        update: &mut dyn FnMut() -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        update()
+    }
+    /// The sums of `column` per day, week and month, or the number of rows if
+    /// there is no column. A day which is in the file twice counts with its
+    /// highest value. `None` if the file cannot sum up itself and the records
+    /// have to be read instead.
+    fn totals(&self, _column: Option<&str>) -> Option<Result<Totals, Box<dyn Error>>> {
+        None
+    }
 }
@@ -0,0 +1,14 @@
+use serde::Deserialize;
+
+/// How the record and the event log are stored.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    /// One CSV file each.
+    #[default]
+    Csv,
+    /// One JSON Lines file each, "<name>.jsonl".
+    Jsonl,
+    /// Tables of one SQLite database, "<name>.sqlite".
+    Sqlite,
+}
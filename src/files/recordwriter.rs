@@ -0,0 +1,397 @@
+use crate::files::RecordFile;
+use std::error::Error;
+use std::io::ErrorKind;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+type MakeRecord = Box<dyn Fn(u32) -> Vec<String> + Send>;
+
+enum WriteRequest {
+    Increment(String, MakeRecord),
+    Append(Vec<String>),
+    Flush(Sender<()>),
+}
+
+/// Writes to a record file from a single worker thread, one write at a time
+/// and in the order in which they were queued. Failed writes are reported to
+/// the error channel, or printed to stderr if there is none.
+pub struct RecordWriter {
+    name: &'static str,
+    requests: Option<Sender<WriteRequest>>,
+    errors: Arc<Mutex<Option<Sender<String>>>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl RecordWriter {
+    /// `name` says what is written in error messages, e.g. "the record".
+    pub fn new(record_file: Arc<Mutex<dyn RecordFile>>, name: &'static str) -> RecordWriter {
+        let (requests, receiver) = channel();
+        let errors = Arc::new(Mutex::new(None));
+        let worker_errors = errors.clone();
+        let worker = thread::spawn(move || work(record_file, name, receiver, worker_errors));
+        RecordWriter {
+            name,
+            requests: Some(requests),
+            errors,
+            worker: Some(worker),
+        }
+    }
+
+    pub fn set_error_sender(&self, errors: Sender<String>) {
+        *self.errors.lock().unwrap() = Some(errors);
+    }
+
+    /// Adds one to the count of the last line if its date is `key` and
+    /// starts a new line with a count of one otherwise. `make_record` turns
+    /// the new count into the line. The last line is read when the write is
+    /// carried out, after all writes queued before it and under the file's
+    /// lock, so that counts of other instances of the app are added to. Files
+    /// which append counts get a new line with the added count instead.
+    pub fn increment<F>(&self, key: String, make_record: F)
+    where
+        F: Fn(u32) -> Vec<String> + Send + 'static,
+    {
+        self.send(WriteRequest::Increment(key, Box::new(make_record)));
+    }
+
+    pub fn append(&self, record: Vec<String>) {
+        self.send(WriteRequest::Append(record));
+    }
+
+    /// Blocks until every write queued so far has been carried out.
+    pub fn flush(&self) {
+        let (done, finished) = channel();
+        if self.send(WriteRequest::Flush(done)) {
+            let _ = finished.recv();
+        }
+    }
+
+    // A write that cannot be queued is reported like one that failed.
+    fn send(&self, request: WriteRequest) -> bool {
+        let sent = match self.requests.as_ref() {
+            Some(requests) => requests.send(request).is_ok(),
+            None => false,
+        };
+        if !sent {
+            report(
+                &self.errors,
+                format!("Could not write to {}: the writer has stopped", self.name),
+            );
+        }
+        sent
+    }
+}
+
+impl Drop for RecordWriter {
+    fn drop(&mut self) {
+        self.requests.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn work(
+    record_file: Arc<Mutex<dyn RecordFile>>,
+    name: &str,
+    requests: Receiver<WriteRequest>,
+    errors: Arc<Mutex<Option<Sender<String>>>>,
+) {
+    for request in requests.iter() {
+        match request {
+            WriteRequest::Increment(key, make_record) => {
+                if let Err(e) = with_retries(|| increment(&record_file, &key, &make_record)) {
+                    report(&errors, format!("Could not write to {}: {}", name, e));
+                }
+            }
+            WriteRequest::Append(record) => {
+                let written = with_retries(|| {
+                    let record_file = record_file.lock().unwrap();
+                    record_file.locked(&mut || record_file.write_record_to_new_line(record.clone()))
+                });
+                if let Err(e) = written {
+                    report(&errors, format!("Could not write to {}: {}", name, e));
+                }
+            }
+            WriteRequest::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+fn increment(
+    record_file: &Arc<Mutex<dyn RecordFile>>,
+    key: &str,
+    make_record: &MakeRecord,
+) -> Result<(), Box<dyn Error>> {
+    let record_file = record_file.lock().unwrap();
+    record_file.locked(
+        &mut || match record_file.get_last_pomodoro_date_and_line_no() {
+            Some((last_key, line_pos)) if last_key == key => {
+                // A count which cannot be read is left for the user to fix
+                // rather than started over.
+                let count = record_file
+                    .get_last_pomodoro_count()
+                    .ok_or_else(|| format!("the count on line {} cannot be read", line_pos))?;
+                if record_file.appends_counts() {
+                    record_file.write_record_to_new_line(make_record(count + 1))
+                } else {
+                    record_file.overwrite_record_in_pos_with(line_pos, make_record(count + 1))
+                }
+            }
+            _ => record_file.write_record_to_new_line(make_record(1)),
+        },
+    )
+}
+
+fn with_retries<F>(mut write: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut() -> Result<(), Box<dyn Error>>,
+{
+    let mut attempt = 1;
+    loop {
+        match write() {
+            Err(e) if attempt < ATTEMPTS && is_transient(e.as_ref()) => {
+                thread::sleep(RETRY_DELAY * attempt);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn is_transient(error: &(dyn Error + 'static)) -> bool {
+    match error.downcast_ref::<std::io::Error>() {
+        Some(e) => matches!(
+            e.kind(),
+            ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut
+        ),
+        None => false,
+    }
+}
+
+fn report(errors: &Arc<Mutex<Option<Sender<String>>>>, message: String) {
+    let errors = errors.lock().unwrap();
+    let unsent = match errors.as_ref() {
+        Some(errors) => errors.send(message).err().map(|e| e.0),
+        None => Some(message),
+    };
+    if let Some(message) = unsent {
+        eprintln!("Error: {}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::files::recordwriter::RecordWriter;
+    use crate::files::{RecordFile, Schema};
+    use std::error::Error;
+    use std::io;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    // Keeps its lines in memory. The first `failures` writes fail with
+    // `failure`.
+    struct MemoryFile {
+        lines: Mutex<Vec<Vec<String>>>,
+        failures: Mutex<u32>,
+        failure: io::ErrorKind,
+        appends_counts: bool,
+    }
+
+    impl MemoryFile {
+        fn failing(failures: u32, failure: io::ErrorKind) -> Arc<Mutex<MemoryFile>> {
+            Arc::new(Mutex::new(MemoryFile {
+                lines: Mutex::new(Vec::new()),
+                failures: Mutex::new(failures),
+                failure,
+                appends_counts: false,
+            }))
+        }
+
+        fn appending_counts() -> Arc<Mutex<MemoryFile>> {
+            let file = MemoryFile::failing(0, io::ErrorKind::Other);
+            file.lock().unwrap().appends_counts = true;
+            file
+        }
+
+        fn fail(&self) -> Result<(), Box<dyn Error>> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(Box::new(io::Error::from(self.failure)));
+            }
+            // Gives a racing write the chance to overtake this one.
+            thread::sleep(Duration::from_millis(10));
+            Ok(())
+        }
+    }
+
+    impl RecordFile for MemoryFile {
+        fn open_or_create(&self, _schema: &Schema) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+        fn write_headers(&self, _headers: &[String]) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+        fn write_record_to_new_line(&self, record: Vec<String>) -> Result<(), Box<dyn Error>> {
+            self.fail()?;
+            self.lines.lock().unwrap().push(record);
+            Ok(())
+        }
+        fn overwrite_record_in_pos_with(
+            &self,
+            pos: usize,
+            record: Vec<String>,
+        ) -> Result<(), Box<dyn Error>> {
+            self.fail()?;
+            self.lines.lock().unwrap()[pos - 1] = record;
+            Ok(())
+        }
+        fn read_records(&self) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+            Ok(self.lines.lock().unwrap().clone())
+        }
+        fn read_headers(&self) -> Result<Vec<String>, Box<dyn Error>> {
+            Ok(Vec::new())
+        }
+        fn get_last_pomodoro_count(&self) -> Option<u32> {
+            self.lines.lock().unwrap().last()?[1].parse().ok()
+        }
+        fn get_last_pomodoro_date_and_line_no(&self) -> Option<(String, usize)> {
+            let lines = self.lines.lock().unwrap();
+            lines.last().map(|line| (line[0].clone(), lines.len()))
+        }
+        fn appends_counts(&self) -> bool {
+            self.appends_counts
+        }
+    }
+
+    fn line(date: &str, count: u32) -> Vec<String> {
+        vec![date.to_string(), count.to_string()]
+    }
+
+    fn increment(writer: &RecordWriter, date: &'static str) {
+        writer.increment(date.to_string(), move |count| line(date, count));
+    }
+
+    #[test]
+    fn writes_are_carried_out_in_order() {
+        let file = MemoryFile::failing(0, io::ErrorKind::Other);
+        let writer = RecordWriter::new(file.clone(), "the record");
+        for _ in 1..=5 {
+            increment(&writer, "2019-07-20");
+        }
+        increment(&writer, "2019-07-21");
+        writer.flush();
+        assert_eq!(
+            file.lock().unwrap().read_records().unwrap(),
+            vec![line("2019-07-20", 5), line("2019-07-21", 1)]
+        );
+    }
+
+    #[test]
+    fn counts_of_other_writers_are_added_to() {
+        let file = MemoryFile::failing(0, io::ErrorKind::Other);
+        let writer = RecordWriter::new(file.clone(), "the record");
+        let other_writer = RecordWriter::new(file.clone(), "the record");
+        for _ in 0..3 {
+            increment(&writer, "2019-07-20");
+            increment(&other_writer, "2019-07-20");
+        }
+        writer.flush();
+        other_writer.flush();
+        assert_eq!(
+            file.lock().unwrap().read_records().unwrap(),
+            vec![line("2019-07-20", 6)]
+        );
+    }
+
+    #[test]
+    fn appends_never_overwrite() {
+        let file = MemoryFile::failing(0, io::ErrorKind::Other);
+        let writer = RecordWriter::new(file.clone(), "the record");
+        writer.append(line("2019-07-20", 1));
+        writer.append(line("2019-07-20", 1));
+        writer.flush();
+        assert_eq!(file.lock().unwrap().read_records().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn files_appending_counts_get_a_line_per_count() {
+        let file = MemoryFile::appending_counts();
+        let writer = RecordWriter::new(file.clone(), "the record");
+        increment(&writer, "2019-07-20");
+        increment(&writer, "2019-07-20");
+        increment(&writer, "2019-07-21");
+        writer.flush();
+        assert_eq!(
+            file.lock().unwrap().read_records().unwrap(),
+            vec![
+                line("2019-07-20", 1),
+                line("2019-07-20", 2),
+                line("2019-07-21", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn unreadable_counts_are_reported_instead_of_overwritten() {
+        let file = MemoryFile::failing(0, io::ErrorKind::Other);
+        let malformed = vec!["2019-07-20".to_string(), "three".to_string()];
+        file.lock()
+            .unwrap()
+            .write_record_to_new_line(malformed.clone())
+            .unwrap();
+        let writer = RecordWriter::new(file.clone(), "the record");
+        let (errors, reported) = channel();
+        writer.set_error_sender(errors);
+        increment(&writer, "2019-07-20");
+        writer.flush();
+        assert_eq!(
+            file.lock().unwrap().read_records().unwrap(),
+            vec![malformed]
+        );
+        assert_eq!(
+            reported.try_recv().unwrap(),
+            "Could not write to the record: the count on line 1 cannot be read"
+        );
+    }
+
+    #[test]
+    fn transient_errors_are_retried() {
+        let file = MemoryFile::failing(2, io::ErrorKind::Interrupted);
+        let writer = RecordWriter::new(file.clone(), "the record");
+        let (errors, reported) = channel();
+        writer.set_error_sender(errors);
+        increment(&writer, "2019-07-20");
+        writer.flush();
+        assert_eq!(
+            file.lock().unwrap().read_records().unwrap(),
+            vec![line("2019-07-20", 1)]
+        );
+        assert!(reported.try_recv().is_err());
+    }
+
+    #[test]
+    fn failed_writes_are_reported() {
+        let file = MemoryFile::failing(1, io::ErrorKind::PermissionDenied);
+        let writer = RecordWriter::new(file.clone(), "the record");
+        let (errors, reported) = channel();
+        writer.set_error_sender(errors);
+        increment(&writer, "2019-07-20");
+        writer.flush();
+        assert!(file.lock().unwrap().read_records().unwrap().is_empty());
+        assert!(reported
+            .try_recv()
+            .unwrap()
+            .starts_with("Could not write to the record"));
+    }
+}
@@ -0,0 +1,129 @@
+const INTEGER_HEADERS: [&str; 4] = [
+    "Number of pomodoros",
+    "Daily goal",
+    "Planned duration",
+    "Actual duration",
+];
+
+/// The columns of a record file in every version of the app. Version 1 is
+/// the first entry of `versions`. Columns are only ever added, so that a file
+/// of an older version can be migrated by looking its columns up by name.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Schema {
+    name: &'static str,
+    versions: Vec<Vec<&'static str>>,
+}
+
+impl Schema {
+    pub fn new(name: &'static str, versions: Vec<Vec<&'static str>>) -> Schema {
+        Schema { name, versions }
+    }
+
+    /// Counts, goals and durations are whole numbers in every schema, the
+    /// other columns text.
+    pub fn is_integer(header: &str) -> bool {
+        INTEGER_HEADERS.contains(&header)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn current_version(&self) -> u32 {
+        self.versions.len() as u32
+    }
+
+    pub fn headers(&self) -> Vec<String> {
+        self.headers_of(self.current_version()).unwrap_or_default()
+    }
+
+    pub fn headers_of(&self, version: u32) -> Option<Vec<String>> {
+        let headers = self.versions.get((version as usize).checked_sub(1)?)?;
+        Some(headers.iter().map(|header| header.to_string()).collect())
+    }
+
+    /// The latest version with exactly these headers.
+    pub fn version_of(&self, headers: &[String]) -> Option<u32> {
+        (1..=self.current_version())
+            .rev()
+            .find(|version| self.headers_of(*version).as_deref() == Some(headers))
+    }
+
+    /// Whether the headers were written by a later version, which adds
+    /// columns to the current ones.
+    pub fn is_newer(&self, headers: &[String]) -> bool {
+        let current = self.headers();
+        headers.len() > current.len() && current.iter().all(|header| headers.contains(header))
+    }
+
+    /// Moves the fields of a row with the given headers to the columns of the
+    /// current version. New columns are left empty.
+    pub fn migrate_row(&self, headers: &[String], row: &[String]) -> Vec<String> {
+        self.headers()
+            .iter()
+            .map(|header| {
+                headers
+                    .iter()
+                    .position(|old_header| old_header == header)
+                    .and_then(|column| row.get(column))
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::files::Schema;
+
+    fn schema() -> Schema {
+        Schema::new(
+            "record",
+            vec![
+                vec!["Date", "Number of pomodoros"],
+                vec!["Date", "Number of pomodoros", "Daily goal"],
+            ],
+        )
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn versions_are_found_by_their_headers() {
+        let schema = schema();
+        assert_eq!(schema.current_version(), 2);
+        assert_eq!(
+            schema.version_of(&strings(&["Date", "Number of pomodoros"])),
+            Some(1)
+        );
+        assert_eq!(schema.version_of(&schema.headers()), Some(2));
+        assert_eq!(schema.version_of(&strings(&["Date", "Count"])), None);
+    }
+
+    #[test]
+    fn later_versions_add_columns() {
+        let schema = schema();
+        assert!(schema.is_newer(&strings(&[
+            "Date",
+            "Number of pomodoros",
+            "Daily goal",
+            "Task"
+        ])));
+        assert!(!schema.is_newer(&schema.headers()));
+        assert!(!schema.is_newer(&strings(&["Date", "Count", "Goal", "Task"])));
+    }
+
+    #[test]
+    fn rows_are_migrated_by_column_name() {
+        assert_eq!(
+            schema().migrate_row(
+                &strings(&["Number of pomodoros", "Date"]),
+                &strings(&["3", "2019-07-20"])
+            ),
+            strings(&["2019-07-20", "3", ""])
+        );
+    }
+}
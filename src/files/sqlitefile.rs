@@ -0,0 +1,568 @@
+use crate::files::{RecordFile, Schema, Totals};
+use chrono::NaiveDate;
+use rusqlite::types::{ToSql, Value};
+use rusqlite::{Connection, OptionalExtension, Row, NO_PARAMS};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+// Task names are kept once in a table of their own and referenced by id.
+const TASK_COLUMN: &str = "Task";
+// Rows are looked up and summed up by day.
+const DATE_COLUMN: &str = "Date";
+
+/// Keeps the rows of one schema in a table of an SQLite database. Several
+/// tables, e.g. the days of the record and the intervals of the event log,
+/// can share a database.
+pub struct SqliteFile {
+    connection: Connection,
+    table: String,
+    schema: Schema,
+}
+
+impl SqliteFile {
+    pub fn new(path: &str, table: &str, schema: Schema) -> Result<SqliteFile, Box<dyn Error>> {
+        if let Some(directory) = Path::new(path).parent() {
+            if !directory.as_os_str().is_empty() {
+                fs::create_dir_all(directory)?;
+            }
+        }
+        let connection = Connection::open(path)?;
+        // Other instances of the app may hold the write lock for a moment.
+        connection.busy_timeout(Duration::from_secs(5))?;
+        Ok(SqliteFile {
+            connection,
+            table: table.to_string(),
+            schema,
+        })
+    }
+
+    fn column_list(&self) -> String {
+        self.schema
+            .headers()
+            .iter()
+            .map(|header| quoted(header))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn select_list(&self) -> String {
+        self.schema
+            .headers()
+            .iter()
+            .map(|header| {
+                if header == TASK_COLUMN {
+                    format!("(SELECT name FROM tasks WHERE id = {})", quoted(header))
+                } else {
+                    quoted(header)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn values(&self, record: Vec<String>) -> Result<Vec<Value>, Box<dyn Error>> {
+        let mut fields = record.into_iter();
+        let mut values = Vec::new();
+        for header in self.schema.headers() {
+            let field = fields.next().unwrap_or_default();
+            values.push(if header == TASK_COLUMN {
+                match self.task_id(&field)? {
+                    Some(id) => Value::Integer(id),
+                    None => Value::Null,
+                }
+            } else if !Schema::is_integer(&header) {
+                Value::Text(field)
+            } else if field.is_empty() {
+                Value::Null
+            } else {
+                Value::Integer(
+                    field
+                        .parse()
+                        .map_err(|_| format!("{} is not a number: {}", header, field))?,
+                )
+            });
+        }
+        Ok(values)
+    }
+
+    fn task_id(&self, name: &str) -> Result<Option<i64>, Box<dyn Error>> {
+        if name.is_empty() {
+            return Ok(None);
+        }
+        self.connection
+            .execute("INSERT OR IGNORE INTO tasks (name) VALUES (?)", &[name])?;
+        Ok(Some(self.connection.query_row(
+            "SELECT id FROM tasks WHERE name = ?",
+            &[name],
+            |row| row.get(0),
+        )?))
+    }
+
+    fn read_rows(&self, sql: &str) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        let columns = self.schema.headers().len();
+        self.query(sql, |row| {
+            (0..columns)
+                .map(|column| row.get::<_, Value>(column).map(field_of))
+                .collect()
+        })
+    }
+
+    // The last row and its position, counted like the lines of a CSV file
+    // with the headers on line 1.
+    fn last_row(&self) -> Option<(Vec<String>, usize)> {
+        let last_row = self
+            .read_rows(&format!(
+                "SELECT {} FROM {} ORDER BY id DESC LIMIT 1",
+                self.select_list(),
+                quoted(&self.table)
+            ))
+            .ok()?
+            .pop()?;
+        let count: i64 = self
+            .connection
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {}", quoted(&self.table)),
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .ok()?;
+        Some((last_row, count as usize + 1))
+    }
+
+    fn column_of(&self, header: &str) -> Option<usize> {
+        self.schema.headers().iter().position(|name| name == header)
+    }
+
+    fn query<T, F>(&self, sql: &str, read_row: F) -> Result<Vec<T>, Box<dyn Error>>
+    where
+        F: FnMut(&Row<'_>) -> rusqlite::Result<T>,
+    {
+        let mut statement = self.connection.prepare(sql)?;
+        let rows = statement.query_map(NO_PARAMS, read_row)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    fn create_or_migrate(&self, schema: &Schema) -> Result<(), Box<dyn Error>> {
+        let version: Option<u32> = self
+            .connection
+            .query_row(
+                "SELECT version FROM schema_versions WHERE name = ?",
+                &[&self.table],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let old_headers = match version {
+            None => {
+                let columns: Vec<String> = schema
+                    .headers()
+                    .iter()
+                    .map(|header| column_definition(header))
+                    .collect();
+                self.connection.execute(
+                    &format!(
+                        "CREATE TABLE {} (id INTEGER PRIMARY KEY AUTOINCREMENT, {})",
+                        quoted(&self.table),
+                        columns.join(", ")
+                    ),
+                    NO_PARAMS,
+                )?;
+                self.set_version(schema.current_version())?;
+                return Ok(());
+            }
+            Some(version) if version > schema.current_version() => {
+                return Err(format!(
+                    "the {} table was written by a newer version of the app (schema version {})",
+                    self.table, version
+                )
+                .into());
+            }
+            Some(version) if version == schema.current_version() => return Ok(()),
+            Some(version) => schema.headers_of(version).unwrap_or_default(),
+        };
+        for header in schema.headers() {
+            if !old_headers.contains(&header) {
+                self.connection.execute(
+                    &format!(
+                        "ALTER TABLE {} ADD COLUMN {}",
+                        quoted(&self.table),
+                        column_definition(&header)
+                    ),
+                    NO_PARAMS,
+                )?;
+            }
+        }
+        self.set_version(schema.current_version())
+    }
+
+    fn set_version(&self, version: u32) -> Result<(), Box<dyn Error>> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO schema_versions (name, version) VALUES (?, ?)",
+            &[&self.table as &dyn ToSql, &version],
+        )?;
+        Ok(())
+    }
+}
+
+impl RecordFile for SqliteFile {
+    fn open_or_create(&self, schema: &Schema) -> Result<(), Box<dyn Error>> {
+        self.connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_versions (
+                name TEXT PRIMARY KEY,
+                version INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS tasks (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE
+            );",
+        )?;
+        self.locked(&mut || self.create_or_migrate(schema))?;
+        if schema.headers().iter().any(|header| header == DATE_COLUMN) {
+            self.connection.execute(
+                &format!(
+                    "CREATE INDEX IF NOT EXISTS {} ON {} ({})",
+                    quoted(&format!("{}_by_date", self.table)),
+                    quoted(&self.table),
+                    quoted(DATE_COLUMN)
+                ),
+                NO_PARAMS,
+            )?;
+        }
+        Ok(())
+    }
+
+    // The headers are the columns of the table.
+    fn write_headers(&self, _headers: &[String]) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn write_record_to_new_line(&self, record: Vec<String>) -> Result<(), Box<dyn Error>> {
+        let values = self.values(record)?;
+        let placeholders = vec!["?"; values.len()].join(", ");
+        self.connection.execute(
+            &format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                quoted(&self.table),
+                self.column_list(),
+                placeholders
+            ),
+            &values,
+        )?;
+        Ok(())
+    }
+
+    /// `pos` counts the rows from 1 as in a CSV file, so that the first row
+    /// of the table is at 2.
+    fn overwrite_record_in_pos_with(
+        &self,
+        pos: usize,
+        record: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        if pos < 2 {
+            return Err(format!("line {} is not in the record", pos).into());
+        }
+        let mut values = self.values(record)?;
+        let assignments = self
+            .schema
+            .headers()
+            .iter()
+            .map(|header| format!("{} = ?", quoted(header)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        values.push(Value::Integer(pos as i64 - 2));
+        let updated = self.connection.execute(
+            &format!(
+                "UPDATE {0} SET {1} WHERE id = (SELECT id FROM {0} ORDER BY id LIMIT 1 OFFSET ?)",
+                quoted(&self.table),
+                assignments
+            ),
+            &values,
+        )?;
+        if updated == 0 {
+            return Err(format!("line {} is not in the record", pos).into());
+        }
+        Ok(())
+    }
+
+    fn read_records(&self) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        self.read_rows(&format!(
+            "SELECT {} FROM {} ORDER BY id",
+            self.select_list(),
+            quoted(&self.table)
+        ))
+    }
+
+    fn read_headers(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self.schema.headers())
+    }
+
+    fn get_last_pomodoro_count(&self) -> Option<u32> {
+        let (last_row, _) = self.last_row()?;
+        last_row
+            .get(self.column_of("Number of pomodoros")?)?
+            .parse()
+            .ok()
+    }
+
+    fn get_last_pomodoro_date_and_line_no(&self) -> Option<(String, usize)> {
+        let (last_row, position) = self.last_row()?;
+        Some((
+            last_row.get(self.column_of(DATE_COLUMN)?)?.clone(),
+            position,
+        ))
+    }
+
+    // An immediate transaction takes the database's write lock at once, so
+    // that no other instance writes between the read and the write.
+    fn locked(
+        &self,
+        update: &mut dyn FnMut() -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.connection.execute_batch("BEGIN IMMEDIATE")?;
+        match update() {
+            Ok(()) => {
+                self.connection.execute_batch("COMMIT")?;
+                Ok(())
+            }
+            Err(e) => {
+                let _ = self.connection.execute_batch("ROLLBACK");
+                Err(e)
+            }
+        }
+    }
+
+    // A week belongs to the ISO year of its Thursday and is counted from the
+    // first week with a Thursday in that year.
+    fn totals(&self, column: Option<&str>) -> Option<Result<Totals, Box<dyn Error>>> {
+        self.column_of(DATE_COLUMN)?;
+        let total = match column {
+            Some(column) => format!("MAX({})", quoted(column)),
+            None => "COUNT(*)".to_string(),
+        };
+        let day_totals = format!(
+            "WITH day_totals (day, total) AS (SELECT {0}, {1} FROM {2} WHERE date({0}) = {0} \
+             GROUP BY {0} HAVING {1} IS NOT NULL)",
+            quoted(DATE_COLUMN),
+            total,
+            quoted(&self.table)
+        );
+        let period = |row: &Row<'_>| {
+            Ok((
+                row.get::<_, i64>(0)? as i32,
+                row.get::<_, i64>(1)? as u32,
+                row.get::<_, i64>(2)? as u32,
+            ))
+        };
+        let totals = || -> Result<Totals, Box<dyn Error>> {
+            let daily = self.query(
+                &format!(
+                    "{} SELECT day, total FROM day_totals ORDER BY day",
+                    day_totals
+                ),
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u32)),
+            )?;
+            Ok(Totals {
+                daily: daily
+                    .into_iter()
+                    .filter_map(|(day, total)| {
+                        let day = NaiveDate::parse_from_str(&day, "%Y-%m-%d").ok()?;
+                        Some((day, total))
+                    })
+                    .collect(),
+                weekly: self.query(
+                    &format!(
+                        "{0} SELECT CAST(strftime('%Y', {1}) AS INTEGER), \
+                         (CAST(strftime('%j', {1}) AS INTEGER) - 1) / 7 + 1, SUM(total) \
+                         FROM day_totals GROUP BY 1, 2 ORDER BY 1, 2",
+                        day_totals, "date(day, '-3 days', 'weekday 4')"
+                    ),
+                    period,
+                )?,
+                monthly: self.query(
+                    &format!(
+                        "{} SELECT CAST(strftime('%Y', day) AS INTEGER), \
+                         CAST(strftime('%m', day) AS INTEGER), SUM(total) \
+                         FROM day_totals GROUP BY 1, 2 ORDER BY 1, 2",
+                        day_totals
+                    ),
+                    period,
+                )?,
+            })
+        };
+        Some(totals())
+    }
+}
+
+fn quoted(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn column_definition(header: &str) -> String {
+    if header == TASK_COLUMN {
+        format!("{} INTEGER REFERENCES tasks (id)", quoted(header))
+    } else if Schema::is_integer(header) {
+        format!("{} INTEGER", quoted(header))
+    } else {
+        format!("{} TEXT", quoted(header))
+    }
+}
+
+fn field_of(value: Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Integer(number) => number.to_string(),
+        Value::Real(number) => number.to_string(),
+        Value::Text(text) => text,
+        Value::Blob(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::files::sqlitefile::SqliteFile;
+    use crate::files::{RecordFile, Schema};
+    use crate::stats::Statistics;
+    use chrono::NaiveDate;
+
+    fn schema() -> Schema {
+        Schema::new(
+            "record",
+            vec![
+                vec!["Date", "Number of pomodoros"],
+                vec!["Date", "Number of pomodoros", "Task"],
+            ],
+        )
+    }
+
+    fn row(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn opened(path: &str) -> SqliteFile {
+        let file = SqliteFile::new(path, "days", schema()).unwrap();
+        file.open_or_create(&schema()).unwrap();
+        file
+    }
+
+    #[test]
+    fn rows_are_written_and_overwritten() {
+        let file = opened(":memory:");
+        assert_eq!(file.get_last_pomodoro_date_and_line_no(), None);
+        file.write_record_to_new_line(row(&["2019-07-20", "1", "write, \"intro\""]))
+            .unwrap();
+        file.write_record_to_new_line(row(&["2019-07-21", "1", ""]))
+            .unwrap();
+        file.overwrite_record_in_pos_with(3, row(&["2019-07-21", "2", "write, \"intro\""]))
+            .unwrap();
+        assert!(file
+            .overwrite_record_in_pos_with(4, row(&["2019-07-22", "1", ""]))
+            .is_err());
+        assert_eq!(
+            file.read_records().unwrap(),
+            vec![
+                row(&["2019-07-20", "1", "write, \"intro\""]),
+                row(&["2019-07-21", "2", "write, \"intro\""])
+            ]
+        );
+        assert_eq!(file.get_last_pomodoro_count(), Some(2));
+        assert_eq!(
+            file.get_last_pomodoro_date_and_line_no(),
+            Some(("2019-07-21".to_string(), 3))
+        );
+        let tasks: i64 = file
+            .connection
+            .query_row("SELECT COUNT(*) FROM tasks", rusqlite::NO_PARAMS, |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(tasks, 1);
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("rp-{}-{}.sqlite", name, std::process::id()));
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn older_tables_get_the_new_columns() {
+        let path = temp_path("migrated");
+        let old_schema = Schema::new("record", vec![vec!["Date", "Number of pomodoros"]]);
+        let old_file = SqliteFile::new(&path, "days", old_schema.clone()).unwrap();
+        old_file.open_or_create(&old_schema).unwrap();
+        old_file
+            .write_record_to_new_line(row(&["2019-07-20", "3"]))
+            .unwrap();
+        drop(old_file);
+        let records = opened(&path).read_records();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(records.unwrap(), vec![row(&["2019-07-20", "3", ""])]);
+    }
+
+    #[test]
+    fn newer_tables_are_refused() {
+        let path = temp_path("newer");
+        drop(opened(&path));
+        let old_schema = Schema::new("record", vec![vec!["Date", "Number of pomodoros"]]);
+        let opened_by_older_app = SqliteFile::new(&path, "days", old_schema.clone())
+            .and_then(|file| file.open_or_create(&old_schema));
+        std::fs::remove_file(&path).unwrap();
+        assert!(opened_by_older_app.is_err());
+    }
+
+    #[test]
+    fn counts_are_integers_and_dates_are_indexed() {
+        let file = opened(":memory:");
+        file.write_record_to_new_line(row(&["2019-07-20", "3", ""]))
+            .unwrap();
+        assert!(file
+            .write_record_to_new_line(row(&["2019-07-21", "three", ""]))
+            .is_err());
+        let count_type: String = file
+            .connection
+            .query_row(
+                "SELECT typeof(\"Number of pomodoros\") FROM days",
+                rusqlite::NO_PARAMS,
+                |row| row.get(0),
+            )
+            .unwrap();
+        let indexes: i64 = file
+            .connection
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND tbl_name = 'days'",
+                rusqlite::NO_PARAMS,
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count_type, "integer");
+        assert_eq!(indexes, 1);
+    }
+
+    #[test]
+    fn totals_are_summed_up_in_sql_like_in_the_statistics() {
+        let file = opened(":memory:");
+        for (date, count) in &[
+            ("2018-12-30", "1"),
+            ("2018-12-31", "2"),
+            ("2019-01-01", "3"),
+            ("2021-01-03", "4"),
+            ("2021-01-03", "5"),
+            ("not a date", "6"),
+        ] {
+            file.write_record_to_new_line(row(&[date, count, ""]))
+                .unwrap();
+        }
+        let today = NaiveDate::from_ymd(2021, 1, 3);
+        let totals = file.totals(Some("Number of pomodoros")).unwrap().unwrap();
+        let statistics = Statistics::new(totals.daily.clone(), today);
+        assert_eq!(totals.daily.len(), 4);
+        assert_eq!(
+            totals.weekly,
+            vec![(2018, 52, 1), (2019, 1, 5), (2020, 53, 5)]
+        );
+        assert_eq!(totals.weekly, statistics.weekly_totals());
+        assert_eq!(totals.monthly, statistics.monthly_totals());
+        let counted = file.totals(None).unwrap().unwrap();
+        assert_eq!(counted.daily.last(), Some(&(today, 2)));
+    }
+}
@@ -0,0 +1,56 @@
+use fs2::FileExt;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+pub fn append_line(path: &str, line: &str) -> Result<(), Box<dyn Error>> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+// The new contents are written to a temporary file next to the record and
+// renamed over it, so that the record is either the old or the new version
+// even if the app is interrupted. The old version is kept as a backup.
+pub fn replace_lines(path: &str, lines: &[String]) -> Result<(), Box<dyn Error>> {
+    let temp_path = sibling_path(path, "tmp");
+    let written = File::create(&temp_path).and_then(|mut temp_file| {
+        for line in lines {
+            writeln!(temp_file, "{}", line)?;
+        }
+        temp_file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+    fs::copy(path, sibling_path(path, "bak"))?;
+    fs::rename(&temp_path, path)?;
+    if let Some(directory) = Path::new(path).parent() {
+        // Makes the rename itself durable. Not possible on every platform.
+        let _ = File::open(directory).and_then(|directory| directory.sync_all());
+    }
+    Ok(())
+}
+
+// The lock is taken on a file of its own, as the record itself is replaced on
+// every rewrite.
+pub fn locked(
+    path: &str,
+    update: &mut dyn FnMut() -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(sibling_path(path, "lock"))?;
+    lock_file.lock_exclusive()?;
+    let result = update();
+    lock_file.unlock()?;
+    result
+}
+
+pub fn sibling_path(path: &str, extension: &str) -> String {
+    format!("{}.{}", path, extension)
+}
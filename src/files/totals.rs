@@ -0,0 +1,10 @@
+use chrono::NaiveDate;
+
+/// Totals per day, ISO week as (year, week, total) and month as (year, month,
+/// total), each oldest first.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Totals {
+    pub daily: Vec<(NaiveDate, u32)>,
+    pub weekly: Vec<(i32, u32, u32)>,
+    pub monthly: Vec<(i32, u32, u32)>,
+}
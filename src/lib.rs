@@ -1,9 +1,12 @@
 pub mod communication;
 pub mod config;
+pub mod eventlog;
 pub mod files;
 pub mod observers;
 pub mod pomodoro_core;
 pub mod record;
+pub mod stats;
+pub mod timing;
 pub mod ui;
 pub mod uimessages;
 pub mod userinterface;
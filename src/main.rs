@@ -1,29 +1,102 @@
+use chrono::prelude::*;
 use rusty_pomodoro::communication::*;
-use rusty_pomodoro::config::YamlConfig;
+use rusty_pomodoro::config::{AppConfig, CliCommand, CliOptions, ConfigLocations, YamlConfig};
+use rusty_pomodoro::eventlog::EventLog;
 use rusty_pomodoro::files::*;
 use rusty_pomodoro::pomodoro_core::Pomodoro;
 use rusty_pomodoro::pomodoro_core::PomodoroConfig;
-use rusty_pomodoro::record::Record;
+use rusty_pomodoro::record::{Record, RecordMode};
+use rusty_pomodoro::stats::Statistics;
+use rusty_pomodoro::timing::DayBoundary;
 use rusty_pomodoro::ui::*;
 use rusty_pomodoro::userinterface::UserInterface;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
 fn main() {
-    let config = PomodoroConfig {
-        pomodoro_time_in_mins: 25_f32,
-        short_break_time_in_mins: 5_f32,
-        long_break_time_in_mins: 15_f32,
+    let cli_options = match CliOptions::from_args(std::env::args_os()) {
+        Ok(options) => options,
+        Err(e) => e.exit(),
+    };
+    let locations = ConfigLocations::from_env();
+    let mut app_config = match load_config(&cli_options, &locations) {
+        Ok(app_config) => app_config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+    cli_options.apply_to(&mut app_config);
+    let location = match app_config.record.location.clone() {
+        Some(location) => location,
+        None => locations.data_dir().to_string_lossy().to_string(),
+    };
+    if let CliCommand::Stats {
+        min_pomodoros,
+        days,
+    } = cli_options.command
+    {
+        if let Err(e) = print_stats(&app_config, location, min_pomodoros, days) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    if let CliCommand::Export {
+        location: export_location,
+        name: export_name,
+    } = &cli_options.command
+    {
+        if let Err(e) = export_summary(&app_config, location, export_location, export_name) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    let config = PomodoroConfig::from(&app_config.timer);
+    let (record, event_log) = if app_config.record.enabled {
+        let mut record = match open_record(&app_config, location.clone()) {
+            Ok(record) => record,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+        };
+        record.set_daily_goal(app_config.goal.daily_pomodoros);
+        if let Err(e) = record.initialize() {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        let event_log = match open_file(
+            &app_config,
+            location,
+            format!("{}-log", app_config.record.name),
+            "intervals",
+            EventLog::schema(),
+        ) {
+            Ok(log_file) => EventLog::new(log_file),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+        };
+        if let Err(e) = event_log.initialize() {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        (Some(record), Some(event_log))
+    } else {
+        (None, None)
     };
-    let filename_and_location: (String, String) = get_record_name_and_collection("rp-config.yml");
-    let record = Record::new(Arc::new(Mutex::new(CsvFile::new(
-        filename_and_location.0,
-        filename_and_location.1,
-    ))));
-    record.initialize();
     let mut pomodoro: Pomodoro;
     let mut no_of_finished_pomodoros = 0;
-    match record.no_of_finished_pomodoros_from_record() {
+    match record
+        .as_ref()
+        .and_then(|record| record.no_of_finished_pomodoros_from_record())
+    {
         Some(no) => {
             pomodoro = Pomodoro::continue_from(no, config);
             no_of_finished_pomodoros = no;
@@ -32,8 +105,33 @@ fn main() {
             pomodoro = Pomodoro::new(config);
         }
     }
-    pomodoro.add_observer(&record);
-    let mut ui = UserInterface::new(Arc::new(SimpleTUI::new()));
+    pomodoro.set_day_boundary(DayBoundary::from(&app_config.day));
+    if let Some(record) = record.as_ref() {
+        pomodoro.add_observer(record);
+    }
+    if let Some(event_log) = event_log.as_ref() {
+        pomodoro.add_observer(event_log);
+    }
+    let tui = Arc::new(SimpleTUI::with_options(
+        app_config.ui.colors,
+        app_config.notification.bell,
+    ));
+    if let Err(e) = tui.enter_raw_mode() {
+        eprintln!("Error: cannot read single key presses: {}", e);
+        process::exit(1);
+    }
+    let mut ui = UserInterface::new(tui.clone());
+    ui.set_task(cli_options.task.clone());
+    ui.set_daily_goal(app_config.goal.daily_pomodoros);
+    if let Some(record) = record.as_ref() {
+        record.set_error_sender(ui.error_sender());
+    }
+    if let Some(event_log) = event_log.as_ref() {
+        event_log.set_error_sender(ui.error_sender());
+    }
+    if let Err(e) = ctrlc::set_handler(ui.interrupter()) {
+        eprintln!("Warning: cannot handle termination signals: {}", e);
+    }
     let cli_receiver = ui.chan_sender();
     let pom_receiver = pomodoro.chan_sender();
     ui.register_receiver(pom_receiver);
@@ -42,26 +140,114 @@ fn main() {
         ui.start(no_of_finished_pomodoros);
     });
     pomodoro.listen_loop();
+    if let Some(record) = record.as_ref() {
+        record.flush();
+    }
+    if let Some(event_log) = event_log.as_ref() {
+        event_log.flush();
+    }
+    tui.restore();
 }
 
-fn get_record_name_and_collection(config_filename: &str) -> (String, String) {
-    let default_filename = "pom-record";
-    let default_location = "./";
-    let mut config = YamlConfig::new(config_filename);
-    match config.parse() {
-        Ok(()) => {
-            let filename: &str;
-            let location: &str;
-            match config.record_filename() {
-                Some(name) => filename = name,
-                None => filename = default_filename,
-            }
-            match config.record_location() {
-                Some(loc) => location = loc,
-                None => location = default_location,
-            }
-            (location.to_string(), filename.to_string())
+fn load_config(
+    cli_options: &CliOptions,
+    locations: &ConfigLocations,
+) -> Result<AppConfig, Box<dyn Error>> {
+    let config_path = match cli_options.config_path.as_ref() {
+        Some(path) if !Path::new(path).is_file() => {
+            return Err(format!("config file {} not found", path).into());
         }
-        Err(_) => (default_location.to_string(), default_filename.to_string()),
+        Some(path) => PathBuf::from(path),
+        None => match locations.find_config_file() {
+            Some(path) => path,
+            None => return Ok(AppConfig::default()),
+        },
+    };
+    let config_path = config_path.to_string_lossy().to_string();
+    let mut yaml_config = YamlConfig::new(&config_path);
+    yaml_config.parse()?;
+    for warning in yaml_config.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(yaml_config.into_config())
+}
+
+fn print_stats(
+    app_config: &AppConfig,
+    location: String,
+    min_pomodoros: u32,
+    days: u32,
+) -> Result<(), Box<dyn Error>> {
+    if !app_config.record.enabled {
+        return Err("statistics need a record file".into());
+    }
+    let record = open_record(app_config, location)?;
+    let today = DayBoundary::from(&app_config.day).date_of(Utc::now());
+    let statistics = match record.totals()? {
+        Some(totals) => Statistics::from_totals(totals, today),
+        None => Statistics::new(record.daily_totals()?, today),
+    }
+    .with_daily_goals(record.daily_goals()?);
+    print!("{}", statistics.report(min_pomodoros, days));
+    Ok(())
+}
+
+fn export_summary(
+    app_config: &AppConfig,
+    location: String,
+    export_location: &str,
+    export_name: &str,
+) -> Result<(), Box<dyn Error>> {
+    if !app_config.record.enabled {
+        return Err("exporting needs a record file".into());
+    }
+    let export_path = Path::new(export_location).join(format!("{}.csv", export_name));
+    if export_path.exists() {
+        return Err(format!("{} already exists", export_path.display()).into());
     }
+    let record = open_record(app_config, location)?;
+    record.export_summary(&CsvFile::new(
+        export_location.to_string(),
+        export_name.to_string(),
+    ))?;
+    println!("Exported the record to {}", export_path.display());
+    Ok(())
+}
+
+// The append-only record is kept in its own file so that the two formats are
+// never mixed up.
+fn open_record(app_config: &AppConfig, location: String) -> Result<Record, Box<dyn Error>> {
+    let (name, table) = match app_config.record.mode {
+        RecordMode::Summary => (app_config.record.name.clone(), "days"),
+        RecordMode::Append => (format!("{}-pomodoros", app_config.record.name), "pomodoros"),
+    };
+    let schema = Record::schema(app_config.record.mode, app_config.record.format);
+    let mut record = Record::new(open_file(app_config, location, name, table, schema)?);
+    record.set_mode(app_config.record.mode);
+    record.set_format(app_config.record.format);
+    record.set_day_boundary(DayBoundary::from(&app_config.day));
+    Ok(record)
+}
+
+// In the SQLite format the record and the event log are tables of one
+// database named after the record.
+fn open_file(
+    app_config: &AppConfig,
+    location: String,
+    name: String,
+    table: &str,
+    schema: Schema,
+) -> Result<Arc<Mutex<dyn RecordFile>>, Box<dyn Error>> {
+    Ok(match app_config.record.format {
+        RecordFormat::Csv => Arc::new(Mutex::new(CsvFile::new(location, name))),
+        RecordFormat::Jsonl => Arc::new(Mutex::new(JsonlFile::new(location, name, schema))),
+        RecordFormat::Sqlite => {
+            let path = Path::new(&location).join(format!("{}.sqlite", app_config.record.name));
+            Arc::new(Mutex::new(SqliteFile::new(
+                &path.to_string_lossy(),
+                table,
+                schema,
+            )?))
+        }
+    })
 }
@@ -1,5 +1,5 @@
-use crate::pomodoro_core::PomodoroStates;
+use crate::pomodoro_core::IntervalEvent;
 
 pub trait Observer {
-    fn callback(&self, next_state: PomodoroStates, finished_pomodoros: u32);
+    fn callback(&self, event: IntervalEvent);
 }
@@ -0,0 +1,28 @@
+use crate::pomodoro_core::{IntervalOutcome, PomodoroStates};
+use chrono::prelude::*;
+use std::time::Duration;
+
+/// Sent to the observers whenever an interval ends.
+#[derive(PartialEq, Clone, Debug)]
+pub struct IntervalEvent {
+    pub state: PomodoroStates,
+    pub next_state: PomodoroStates,
+    pub finished_pomodoros: u32,
+    pub outcome: IntervalOutcome,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub planned_secs: u64,
+    pub paused: Duration,
+    pub task: Option<String>,
+}
+
+impl IntervalEvent {
+    /// The time the interval ran for, without the time it was paused.
+    pub fn actual_secs(&self) -> u64 {
+        let paused =
+            chrono::Duration::from_std(self.paused).unwrap_or_else(|_| chrono::Duration::zero());
+        (self.ended_at - self.started_at - paused)
+            .num_seconds()
+            .max(0) as u64
+    }
+}
@@ -0,0 +1,19 @@
+use std::fmt;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum IntervalOutcome {
+    Completed,
+    Skipped,
+    Aborted,
+}
+
+impl fmt::Display for IntervalOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            IntervalOutcome::Completed => "completed",
+            IntervalOutcome::Skipped => "skipped",
+            IntervalOutcome::Aborted => "aborted",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -0,0 +1,25 @@
+use crate::pomodoro_core::PomodoroStates;
+
+/// Sent to the user interface on every tick of a running interval.
+#[derive(PartialEq, Clone, Debug)]
+pub struct IntervalProgress {
+    pub state: PomodoroStates,
+    pub next_state: PomodoroStates,
+    pub elapsed_secs: u64,
+    pub total_secs: u64,
+}
+
+impl IntervalProgress {
+    pub fn remaining_secs(&self) -> u64 {
+        self.total_secs.saturating_sub(self.elapsed_secs)
+    }
+
+    /// How much of the interval is over, from 0 to 1.
+    pub fn fraction(&self) -> f64 {
+        if self.total_secs == 0 {
+            1_f64
+        } else {
+            (self.elapsed_secs as f64 / self.total_secs as f64).min(1_f64)
+        }
+    }
+}
@@ -1,7 +1,13 @@
+mod intervalevent;
+mod intervaloutcome;
+mod intervalprogress;
 mod pomodoro;
 mod pomodoroconfig;
 mod pomodorostates;
 
+pub use intervalevent::IntervalEvent;
+pub use intervaloutcome::IntervalOutcome;
+pub use intervalprogress::IntervalProgress;
 pub use pomodoro::Pomodoro;
 pub use pomodoroconfig::PomodoroConfig;
 pub use pomodorostates::PomodoroStates;
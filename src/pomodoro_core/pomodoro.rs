@@ -1,26 +1,48 @@
 use crate::communication::*;
 use crate::observers::*;
 use crate::pomodoro_core::*;
-use std::sync::mpsc::{channel, Receiver, Sender};
-use std::thread;
-use std::time::Duration;
+use crate::timing::{Clock, DayBoundary, SystemClock};
+use chrono::prelude::*;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// How often the date is looked at while waiting for the user to proceed.
+const DAY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+enum Countdown {
+    // With the time spent paused.
+    Ended(IntervalOutcome, Duration),
+    Cancelled,
+}
 
 pub struct Pomodoro<'a> {
     pub current_state: PomodoroStates,
     pub finished_pomodoros: u32,
     pub next_state: PomodoroStates,
     pub state_observers: Vec<&'a dyn Observer>,
+    pub task: Option<String>,
+    clock: Arc<dyn Clock>,
+    day_boundary: DayBoundary,
     long_break_time_in_secs: u64,
-    no_of_breaks: u8,
+    no_of_breaks: u32,
     pom_sender: Option<Sender<PomodoroChannel>>,
     pomodoro_time_in_secs: u64,
+    pomodoros_before_long_break: u32,
     short_break_time_in_secs: u64,
+    tick: Duration,
+    today: NaiveDate,
     ui_receiver: Option<Receiver<UIChannel>>,
 }
 
 impl<'a> Pomodoro<'a> {
     pub fn new(config: PomodoroConfig) -> Pomodoro<'a> {
+        let clock = Arc::new(SystemClock::new());
+        let day_boundary = DayBoundary::default();
         Pomodoro {
+            today: day_boundary.date_of(clock.now()),
+            clock,
+            day_boundary,
             finished_pomodoros: 0,
             no_of_breaks: 0,
             pomodoro_time_in_secs: Pomodoro::convert_minutes_to_seconds(
@@ -32,9 +54,12 @@ impl<'a> Pomodoro<'a> {
             long_break_time_in_secs: Pomodoro::convert_minutes_to_seconds(
                 config.long_break_time_in_mins,
             ),
+            pomodoros_before_long_break: config.pomodoros_before_long_break,
+            tick: Duration::from_secs_f32(config.tick_in_secs),
             current_state: PomodoroStates::Pomodoro,
             next_state: PomodoroStates::Pomodoro,
             state_observers: Vec::new(),
+            task: None,
             ui_receiver: None,
             pom_sender: None,
         }
@@ -47,15 +72,31 @@ impl<'a> Pomodoro<'a> {
         }
     }
 
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+        self.today = self.day_boundary.date_of(self.clock.now());
+    }
+
+    pub fn set_day_boundary(&mut self, day_boundary: DayBoundary) {
+        self.day_boundary = day_boundary;
+        self.today = self.day_boundary.date_of(self.clock.now());
+    }
+
     pub fn add_observer(&mut self, observer: &'a impl Observer) {
         self.state_observers.push(observer);
     }
 
     pub fn listen_loop(&mut self) {
-        if let Some(channel) = self.ui_receiver.as_ref() {
-            if let Ok(message) = channel.recv() {
+        if self.ui_receiver.is_some() {
+            if let Some(message) = self.receive_while_waiting() {
                 match message {
                     UIChannel::Proceed => self.run_pom_cycle(),
+                    UIChannel::Pause | UIChannel::Resume => self.listen_loop(),
+                    UIChannel::Skip | UIChannel::Abort => self.listen_loop(),
+                    UIChannel::Task(task) => {
+                        self.task = task;
+                        self.listen_loop()
+                    }
                     UIChannel::Cancel => (),
                 }
             }
@@ -63,70 +104,233 @@ impl<'a> Pomodoro<'a> {
     }
 
     fn run_pom_cycle(&mut self) {
-        match self.next_state {
+        let started_at = self.clock.now();
+        let countdown = match self.next_state {
             PomodoroStates::Pomodoro => {
-                if self.no_of_breaks == 3 {
-                    self.next_state = PomodoroStates::LongBreak;
-                } else {
-                    self.next_state = PomodoroStates::ShortBreak;
-                }
                 self.current_state = PomodoroStates::Pomodoro;
-                self.wait_for_seconds(self.pomodoro_time_in_secs);
-                self.finished_pomodoros += 1;
+                self.wait_for_seconds(self.pomodoro_time_in_secs)
             }
             PomodoroStates::ShortBreak => {
                 self.next_state = PomodoroStates::Pomodoro;
                 self.current_state = PomodoroStates::ShortBreak;
                 self.no_of_breaks += 1;
-                self.wait_for_seconds(self.short_break_time_in_secs);
+                self.wait_for_seconds(self.short_break_time_in_secs)
             }
             PomodoroStates::LongBreak => {
                 self.next_state = PomodoroStates::Pomodoro;
                 self.current_state = PomodoroStates::LongBreak;
                 self.no_of_breaks = 0;
-                self.wait_for_seconds(self.long_break_time_in_secs);
+                self.wait_for_seconds(self.long_break_time_in_secs)
+            }
+        };
+        let (outcome, paused) = match countdown {
+            Countdown::Ended(outcome, paused) => (outcome, paused),
+            Countdown::Cancelled => return,
+        };
+        let ended_at = self.clock.now();
+        self.roll_over_day(ended_at);
+        // Only a pomodoro which ran its full length counts. A skipped one
+        // still moves on to the break.
+        if self.current_state == PomodoroStates::Pomodoro {
+            match outcome {
+                IntervalOutcome::Completed => {
+                    self.next_state = self.break_after_pomodoro();
+                    self.finished_pomodoros += 1;
+                }
+                IntervalOutcome::Skipped => self.next_state = self.break_after_pomodoro(),
+                IntervalOutcome::Aborted => self.next_state = PomodoroStates::Pomodoro,
             }
         }
-        self.notify();
+        self.notify(IntervalEvent {
+            state: self.current_state.clone(),
+            next_state: self.next_state.clone(),
+            finished_pomodoros: self.finished_pomodoros,
+            outcome,
+            started_at,
+            ended_at,
+            planned_secs: self.interval_time_in_secs(&self.current_state),
+            paused,
+            task: self.task.clone(),
+        });
         if let Some(channel) = self.pom_sender.as_ref() {
             let res = channel.send(PomodoroChannel::Completed(
                 self.next_state.clone(),
                 self.finished_pomodoros,
+                self.task.clone(),
             ));
-            if let Err(e) = res {
-                panic!("Some error between threads {}", e);
+            if res.is_err() {
+                // The user interface has gone away, so nobody is left to proceed.
+                return;
             }
             self.listen_loop();
         }
     }
 
-    fn wait_for_seconds(&self, seconds: u64) {
-        let mut remaining_secs = seconds;
-        let notify_every = 5;
-        self.send_update(remaining_secs);
-        while remaining_secs > 5 {
-            thread::sleep(Duration::from_secs(notify_every));
-            remaining_secs -= notify_every;
-            self.send_update(remaining_secs);
+    // The user interface is told when a new day starts, so that the count
+    // shown while waiting does not stay at yesterday's.
+    fn receive_while_waiting(&mut self) -> Option<UIChannel> {
+        loop {
+            if self.roll_over_day(self.clock.now()) {
+                if let Some(channel) = self.pom_sender.as_ref() {
+                    let _ = channel.send(PomodoroChannel::DayStarted(self.finished_pomodoros));
+                }
+            }
+            let deadline = self.clock.instant() + DAY_CHECK_INTERVAL;
+            let received = match self.ui_receiver.as_ref() {
+                Some(channel) => self.clock.receive_until(channel, deadline),
+                None => return None,
+            };
+            match received {
+                Ok(message) => return Some(message),
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+
+    fn break_after_pomodoro(&self) -> PomodoroStates {
+        if self.no_of_breaks + 1 >= self.pomodoros_before_long_break {
+            PomodoroStates::LongBreak
+        } else {
+            PomodoroStates::ShortBreak
         }
     }
 
-    fn send_update(&self, remaining_secs: u64) {
+    /// A session which is left running into the next day counts that day's
+    /// pomodoros from zero. An interval belongs to the day it ends on.
+    fn roll_over_day(&mut self, now: DateTime<Utc>) -> bool {
+        let today = self.day_boundary.date_of(now);
+        if today == self.today {
+            return false;
+        }
+        self.today = today;
+        self.finished_pomodoros = 0;
+        true
+    }
+
+    fn wait_for_seconds(&self, seconds: u64) -> Countdown {
+        // Updates are scheduled from the start of the interval rather than
+        // from when the previous one went out, so that no time is lost to
+        // drift and the interval ends exactly at its deadline.
+        let started_at = self.clock.instant();
+        let mut deadline = started_at + Duration::from_secs(seconds);
+        let mut next_update = started_at + self.tick;
+        let mut paused = Duration::from_secs(0);
+        self.send_update(self.progress(seconds, deadline - started_at));
+        if seconds == 0 {
+            return Countdown::Ended(IntervalOutcome::Completed, paused);
+        }
+        loop {
+            match self.receive_until(next_update.min(deadline)) {
+                Err(RecvTimeoutError::Timeout) => {
+                    if next_update >= deadline {
+                        return Countdown::Ended(IntervalOutcome::Completed, paused);
+                    }
+                    self.send_update(self.progress(seconds, deadline - next_update));
+                    next_update += self.tick;
+                }
+                Ok(UIChannel::Pause) => {
+                    let remaining = deadline.saturating_duration_since(self.clock.instant());
+                    if let Some(countdown) =
+                        self.wait_while_paused(self.progress(seconds, remaining), &mut paused)
+                    {
+                        return countdown;
+                    }
+                    let resumed_at = self.clock.instant();
+                    deadline = resumed_at + remaining;
+                    next_update = resumed_at + self.tick;
+                    self.send_update(self.progress(seconds, remaining));
+                }
+                Ok(UIChannel::Skip) => return Countdown::Ended(IntervalOutcome::Skipped, paused),
+                Ok(UIChannel::Abort) => return Countdown::Ended(IntervalOutcome::Aborted, paused),
+                Ok(UIChannel::Cancel) | Err(_) => return Countdown::Cancelled,
+                Ok(UIChannel::Proceed) | Ok(UIChannel::Resume) | Ok(UIChannel::Task(_)) => (),
+            }
+        }
+    }
+
+    fn receive_until(&self, deadline: Instant) -> Result<UIChannel, RecvTimeoutError> {
+        match self.ui_receiver.as_ref() {
+            Some(channel) => self.clock.receive_until(channel, deadline),
+            None => {
+                self.clock
+                    .sleep(deadline.saturating_duration_since(self.clock.instant()));
+                Err(RecvTimeoutError::Timeout)
+            }
+        }
+    }
+
+    // The time until the interval is resumed, skipped or aborted is added to
+    // `paused`.
+    fn wait_while_paused(
+        &self,
+        progress: IntervalProgress,
+        paused: &mut Duration,
+    ) -> Option<Countdown> {
+        let paused_at = self.clock.instant();
         if let Some(channel) = self.pom_sender.as_ref() {
-            channel
-                .send(PomodoroChannel::Update(remaining_secs))
-                .unwrap();
+            let _ = channel.send(PomodoroChannel::Paused(progress));
         }
+        let outcome = match self.ui_receiver.as_ref() {
+            Some(channel) => loop {
+                match channel.recv() {
+                    Ok(UIChannel::Resume) => break None,
+                    Ok(UIChannel::Skip) => break Some(IntervalOutcome::Skipped),
+                    Ok(UIChannel::Abort) => break Some(IntervalOutcome::Aborted),
+                    Ok(UIChannel::Cancel) | Err(_) => return Some(Countdown::Cancelled),
+                    Ok(UIChannel::Pause) | Ok(UIChannel::Proceed) | Ok(UIChannel::Task(_)) => (),
+                }
+            },
+            None => None,
+        };
+        *paused += self.clock.instant().saturating_duration_since(paused_at);
+        outcome.map(|outcome| Countdown::Ended(outcome, *paused))
     }
 
-    fn notify(&self) {
+    fn progress(&self, total_secs: u64, remaining: Duration) -> IntervalProgress {
+        let next_state = match self.current_state {
+            PomodoroStates::Pomodoro => self.break_after_pomodoro(),
+            PomodoroStates::ShortBreak | PomodoroStates::LongBreak => PomodoroStates::Pomodoro,
+        };
+        IntervalProgress {
+            state: self.current_state.clone(),
+            next_state,
+            elapsed_secs: total_secs.saturating_sub(whole_seconds(remaining)),
+            total_secs,
+        }
+    }
+
+    fn send_update(&self, progress: IntervalProgress) {
+        if let Some(channel) = self.pom_sender.as_ref() {
+            let _ = channel.send(PomodoroChannel::Update(progress));
+        }
+    }
+
+    fn notify(&self, event: IntervalEvent) {
         for observer in self.state_observers.iter() {
-            observer.callback(self.next_state.clone(), self.finished_pomodoros);
+            observer.callback(event.clone());
+        }
+    }
+
+    fn interval_time_in_secs(&self, state: &PomodoroStates) -> u64 {
+        match state {
+            PomodoroStates::Pomodoro => self.pomodoro_time_in_secs,
+            PomodoroStates::ShortBreak => self.short_break_time_in_secs,
+            PomodoroStates::LongBreak => self.long_break_time_in_secs,
         }
     }
 
     fn convert_minutes_to_seconds(minutes: f32) -> u64 {
-        (minutes * (60_f32)) as u64
+        (minutes * (60_f32)).round() as u64
+    }
+}
+
+// Rounded up, so that the last second of an interval shows as 1 and not 0.
+fn whole_seconds(duration: Duration) -> u64 {
+    if duration.subsec_nanos() > 0 {
+        duration.as_secs() + 1
+    } else {
+        duration.as_secs()
     }
 }
 
@@ -149,19 +353,28 @@ mod tests {
     extern crate simulacrum;
     use crate::communication::*;
     use crate::observers::Observer;
+    use crate::pomodoro_core::pomodorostates::PomodoroStates;
     use crate::pomodoro_core::*;
-    #[macro_use]
+    use crate::timing::fakeclock::FakeClock;
+    use crate::timing::DayBoundary;
+    use chrono::prelude::*;
     use simulacrum::*;
-    use crate::pomodoro_core::pomodorostates::PomodoroStates;
     use std::sync::mpsc::channel;
+    use std::sync::Arc;
     use std::thread;
     use std::time::Duration;
 
+    fn fake_clock() -> Arc<FakeClock> {
+        Arc::new(FakeClock::new(Utc.ymd(2019, 7, 20).and_hms(12, 0, 0)))
+    }
+
     fn zero_time_pom_config() -> PomodoroConfig {
         PomodoroConfig {
             pomodoro_time_in_mins: 0 as f32,
             short_break_time_in_mins: 0 as f32,
             long_break_time_in_mins: 0 as f32,
+            pomodoros_before_long_break: 4,
+            tick_in_secs: 1_f32,
         }
     }
 
@@ -173,7 +386,7 @@ mod tests {
         pom.register_receiver(receiver);
         thread::spawn(move || {
             thread::sleep(Duration::from_micros(10));
-            sender.send(UIChannel::Proceed);
+            sender.send(UIChannel::Proceed).unwrap();
         });
         pom.listen_loop();
         assert_eq!(pom.next_state, PomodoroStates::ShortBreak);
@@ -188,18 +401,18 @@ mod tests {
         pom.register_receiver(receiver);
         let pom_receiver = pom.chan_sender();
         let handle = thread::spawn(move || {
-            sender.send(UIChannel::Proceed);
+            sender.send(UIChannel::Proceed).unwrap();
             let mut next_pom_state = PomodoroStates::Pomodoro;
             while next_pom_state != PomodoroStates::LongBreak {
-                if let Ok(message) = pom_receiver.recv() {
-                    if let PomodoroChannel::Completed(next_state, finished_pomodoros) = message {
-                        next_pom_state = next_state;
-                        sender.send(UIChannel::Proceed);
-                    }
+                if let Ok(PomodoroChannel::Completed(next_state, _finished_pomodoros, _task)) =
+                    pom_receiver.recv()
+                {
+                    next_pom_state = next_state;
+                    sender.send(UIChannel::Proceed).unwrap();
                 }
             }
-            if let Ok(message) = pom_receiver.recv() {
-                sender.send(UIChannel::Cancel);
+            if let Ok(_message) = pom_receiver.recv() {
+                sender.send(UIChannel::Cancel).unwrap();
             }
         });
         pom.listen_loop();
@@ -219,7 +432,7 @@ mod tests {
         create_mock! {
             impl Observer for MockObserver (self) {
                 expect_callback("callback"):
-                fn callback(&self, next_state: PomodoroStates, finished_pomodoros: u32);
+                fn callback(&self, event: IntervalEvent);
             }
         }
         let observer = MockObserver::new();
@@ -235,14 +448,21 @@ mod tests {
         create_mock! {
             impl Observer for MockObserver (self) {
                 expect_callback("callback"):
-                fn callback(&self, next_state: PomodoroStates, finished_pomodoros: u32);
+                fn callback(&self, event: IntervalEvent);
             }
         }
         let mut observer = MockObserver::new();
         observer
             .expect_callback()
             .called_once()
-            .with(params!(PomodoroStates::ShortBreak, 1));
+            .with(passes(|event: &IntervalEvent| {
+                event.state == PomodoroStates::Pomodoro
+                    && event.next_state == PomodoroStates::ShortBreak
+                    && event.finished_pomodoros == 1
+                    && event.outcome == IntervalOutcome::Completed
+                    && event.planned_secs == 0
+                    && event.started_at <= event.ended_at
+            }));
         let pom_config = zero_time_pom_config();
         let mut pom = Pomodoro::new(pom_config);
         pom.add_observer(&observer);
@@ -250,38 +470,549 @@ mod tests {
         pom.register_receiver(receiver);
         thread::spawn(move || {
             thread::sleep(Duration::from_micros(10));
-            sender.send(UIChannel::Proceed);
+            sender.send(UIChannel::Proceed).unwrap();
+        });
+        pom.listen_loop();
+    }
+
+    fn count_down(pom: &mut Pomodoro, clock: Arc<FakeClock>, step: Duration) -> Vec<u64> {
+        let (sender, receiver) = channel();
+        pom.register_receiver(receiver);
+        let pom_receiver = pom.chan_sender();
+        let handle = thread::spawn(move || {
+            sender.send(UIChannel::Proceed).unwrap();
+            let mut updates = vec![];
+            loop {
+                match pom_receiver.recv().unwrap() {
+                    PomodoroChannel::Update(progress) => {
+                        updates.push(progress.remaining_secs());
+                        clock.advance(step);
+                    }
+                    PomodoroChannel::Paused(_progress) => (),
+                    PomodoroChannel::DayStarted(_finished_pomodoros) => (),
+                    PomodoroChannel::Completed(_next_state, _finished_pomodoros, _task) => {
+                        sender.send(UIChannel::Cancel).unwrap();
+                        return updates;
+                    }
+                }
+            }
+        });
+        pom.listen_loop();
+        handle.join().unwrap()
+    }
+
+    #[test]
+    fn updates_are_sent_every_second_until_the_end() {
+        create_mock! {
+            impl Observer for MockObserver (self) {
+                expect_callback("callback"):
+                fn callback(&self, event: IntervalEvent);
+            }
+        }
+        let mut observer = MockObserver::new();
+        observer
+            .expect_callback()
+            .called_once()
+            .with(passes(|event: &IntervalEvent| {
+                event.planned_secs == 12 && event.actual_secs() == 12
+            }));
+        let mut pom = Pomodoro::new(PomodoroConfig {
+            pomodoro_time_in_mins: 0.2_f32,
+            short_break_time_in_mins: 0 as f32,
+            long_break_time_in_mins: 0 as f32,
+            pomodoros_before_long_break: 4,
+            tick_in_secs: 1_f32,
+        });
+        let clock = fake_clock();
+        pom.set_clock(clock.clone());
+        pom.add_observer(&observer);
+        let updates = count_down(&mut pom, clock, Duration::from_secs(1));
+        assert_eq!(updates, (1..=12).rev().collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn tick_rate_is_configurable() {
+        let mut pom = Pomodoro::new(PomodoroConfig {
+            pomodoro_time_in_mins: 0.2_f32,
+            short_break_time_in_mins: 0 as f32,
+            long_break_time_in_mins: 0 as f32,
+            pomodoros_before_long_break: 4,
+            tick_in_secs: 5_f32,
+        });
+        let clock = fake_clock();
+        pom.set_clock(clock.clone());
+        let updates = count_down(&mut pom, clock, Duration::from_secs(5));
+        assert_eq!(updates, vec![12, 7, 2]);
+    }
+
+    #[test]
+    fn pausing_keeps_the_remaining_time() {
+        let mut pom = Pomodoro::new(PomodoroConfig {
+            pomodoro_time_in_mins: 0.1_f32,
+            short_break_time_in_mins: 0 as f32,
+            long_break_time_in_mins: 0 as f32,
+            pomodoros_before_long_break: 4,
+            tick_in_secs: 1_f32,
+        });
+        let clock = fake_clock();
+        pom.set_clock(clock.clone());
+        let (sender, receiver) = channel();
+        pom.register_receiver(receiver);
+        let pom_receiver = pom.chan_sender();
+        let handle = thread::spawn(move || {
+            let mut paused_at = None;
+            let mut updates: Vec<u64> = vec![];
+            sender.send(UIChannel::Proceed).unwrap();
+            loop {
+                match pom_receiver.recv().unwrap() {
+                    PomodoroChannel::Update(progress) => {
+                        updates.push(progress.remaining_secs());
+                        if paused_at.is_none() {
+                            clock.advance(Duration::from_millis(500));
+                            sender.send(UIChannel::Pause).unwrap();
+                        } else {
+                            clock.advance(Duration::from_secs(1));
+                        }
+                    }
+                    PomodoroChannel::Paused(progress) => {
+                        paused_at = Some(progress.remaining_secs());
+                        // Time spent paused does not count.
+                        clock.advance(Duration::from_secs(60));
+                        sender.send(UIChannel::Resume).unwrap();
+                    }
+                    PomodoroChannel::DayStarted(_finished_pomodoros) => (),
+                    PomodoroChannel::Completed(_next_state, _finished_pomodoros, _task) => {
+                        sender.send(UIChannel::Cancel).unwrap();
+                        break;
+                    }
+                }
+            }
+            (paused_at, updates)
         });
         pom.listen_loop();
+        let (paused_at, updates) = handle.join().unwrap();
+        assert_eq!(paused_at, Some(6));
+        assert_eq!(updates, vec![6, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(pom.finished_pomodoros, 1);
     }
 
     #[test]
-    fn updates_are_sent_correctly() {
+    fn paused_time_is_not_part_of_the_actual_duration() {
+        create_mock! {
+            impl Observer for MockObserver (self) {
+                expect_callback("callback"):
+                fn callback(&self, event: IntervalEvent);
+            }
+        }
+        let mut observer = MockObserver::new();
+        observer
+            .expect_callback()
+            .called_once()
+            .with(passes(|event: &IntervalEvent| {
+                (event.ended_at - event.started_at).num_seconds() == 72
+                    && event.paused == Duration::from_secs(60)
+                    && event.actual_secs() == 12
+            }));
         let mut pom = Pomodoro::new(PomodoroConfig {
-            pomodoro_time_in_mins: 0.2 as f32,
+            pomodoro_time_in_mins: 0.2_f32,
             short_break_time_in_mins: 0 as f32,
             long_break_time_in_mins: 0 as f32,
+            pomodoros_before_long_break: 4,
+            tick_in_secs: 1_f32,
         });
+        let clock = fake_clock();
+        pom.set_clock(clock.clone());
+        pom.add_observer(&observer);
         let (sender, receiver) = channel();
         pom.register_receiver(receiver);
         let pom_receiver = pom.chan_sender();
-        let mut actual_results: Vec<(u64)> = vec![];
         let handle = thread::spawn(move || {
-            // Decreasing this number may cause travis windows tests to fail
-            thread::sleep(Duration::from_micros(30));
-            sender.send(UIChannel::Proceed);
-            for _ in 0..3 {
+            let mut paused = false;
+            sender.send(UIChannel::Proceed).unwrap();
+            loop {
                 match pom_receiver.recv().unwrap() {
-                    PomodoroChannel::Update(remaining_secs) => actual_results.push(remaining_secs),
-                    PomodoroChannel::Completed(_next_state, _finished_pomodoros) => (),
+                    PomodoroChannel::Update(progress) => {
+                        if progress.remaining_secs() == 6 && !paused {
+                            sender.send(UIChannel::Pause).unwrap();
+                        } else {
+                            clock.advance(Duration::from_secs(1));
+                        }
+                    }
+                    PomodoroChannel::Paused(_progress) => {
+                        paused = true;
+                        clock.advance(Duration::from_secs(60));
+                        sender.send(UIChannel::Resume).unwrap();
+                    }
+                    PomodoroChannel::DayStarted(_finished_pomodoros) => (),
+                    PomodoroChannel::Completed(_next_state, _finished_pomodoros, _task) => {
+                        sender.send(UIChannel::Cancel).unwrap();
+                        break;
+                    }
                 }
             }
-            actual_results
         });
         pom.listen_loop();
-        let actual_results = handle.join().unwrap();
-        assert_eq!(actual_results[0], 12 as u64);
-        assert_eq!(actual_results[1], 7 as u64);
-        assert_eq!(actual_results[2], 2 as u64);
+        handle.join().unwrap();
+    }
+
+    fn run_until_first_completion(pom: &mut Pomodoro, command: UIChannel) -> (PomodoroStates, u32) {
+        let (sender, receiver) = channel();
+        pom.register_receiver(receiver);
+        let pom_receiver = pom.chan_sender();
+        let handle = thread::spawn(move || {
+            sender.send(UIChannel::Proceed).unwrap();
+            let mut command = Some(command);
+            loop {
+                match pom_receiver.recv().unwrap() {
+                    PomodoroChannel::Update(_progress) => {
+                        if let Some(command) = command.take() {
+                            sender.send(command).unwrap();
+                        }
+                    }
+                    PomodoroChannel::Paused(_progress) => (),
+                    PomodoroChannel::DayStarted(_finished_pomodoros) => (),
+                    PomodoroChannel::Completed(next_state, finished_pomodoros, _task) => {
+                        sender.send(UIChannel::Cancel).unwrap();
+                        return (next_state, finished_pomodoros);
+                    }
+                }
+            }
+        });
+        pom.listen_loop();
+        handle.join().unwrap()
+    }
+
+    #[test]
+    fn skipping_a_pomodoro_does_not_count_it() {
+        let mut pom = Pomodoro::new(PomodoroConfig {
+            pomodoro_time_in_mins: 1_f32,
+            short_break_time_in_mins: 0_f32,
+            long_break_time_in_mins: 0_f32,
+            pomodoros_before_long_break: 4,
+            tick_in_secs: 1_f32,
+        });
+        let (next_state, finished_pomodoros) =
+            run_until_first_completion(&mut pom, UIChannel::Skip);
+        assert_eq!(next_state, PomodoroStates::ShortBreak);
+        assert_eq!(finished_pomodoros, 0);
+    }
+
+    #[test]
+    fn aborting_a_pomodoro_does_not_count_it() {
+        let mut pom = Pomodoro::continue_from(
+            3,
+            PomodoroConfig {
+                pomodoro_time_in_mins: 1_f32,
+                short_break_time_in_mins: 0_f32,
+                long_break_time_in_mins: 0_f32,
+                pomodoros_before_long_break: 4,
+                tick_in_secs: 1_f32,
+            },
+        );
+        let (next_state, finished_pomodoros) =
+            run_until_first_completion(&mut pom, UIChannel::Abort);
+        assert_eq!(next_state, PomodoroStates::Pomodoro);
+        assert_eq!(finished_pomodoros, 3);
+    }
+
+    #[test]
+    fn skipping_a_break_starts_the_next_pomodoro() {
+        let mut pom = Pomodoro::new(PomodoroConfig {
+            pomodoro_time_in_mins: 0_f32,
+            short_break_time_in_mins: 1_f32,
+            long_break_time_in_mins: 0_f32,
+            pomodoros_before_long_break: 4,
+            tick_in_secs: 1_f32,
+        });
+        pom.next_state = PomodoroStates::ShortBreak;
+        let (next_state, _finished_pomodoros) =
+            run_until_first_completion(&mut pom, UIChannel::Skip);
+        assert_eq!(pom.current_state, PomodoroStates::ShortBreak);
+        assert_eq!(next_state, PomodoroStates::Pomodoro);
+    }
+
+    #[test]
+    fn observers_are_told_about_aborted_pomodoros() {
+        create_mock! {
+            impl Observer for MockObserver (self) {
+                expect_callback("callback"):
+                fn callback(&self, event: IntervalEvent);
+            }
+        }
+        let mut observer = MockObserver::new();
+        observer
+            .expect_callback()
+            .called_once()
+            .with(passes(|event: &IntervalEvent| {
+                event.state == PomodoroStates::Pomodoro
+                    && event.next_state == PomodoroStates::Pomodoro
+                    && event.finished_pomodoros == 0
+                    && event.outcome == IntervalOutcome::Aborted
+                    && event.planned_secs == 60
+                    && event.actual_secs() < 60
+            }));
+        let mut pom = Pomodoro::new(PomodoroConfig {
+            pomodoro_time_in_mins: 1_f32,
+            short_break_time_in_mins: 0_f32,
+            long_break_time_in_mins: 0_f32,
+            pomodoros_before_long_break: 4,
+            tick_in_secs: 1_f32,
+        });
+        pom.add_observer(&observer);
+        run_until_first_completion(&mut pom, UIChannel::Abort);
+    }
+
+    #[test]
+    fn long_break_interval_is_configurable() {
+        let mut pom = Pomodoro::new(PomodoroConfig {
+            pomodoros_before_long_break: 2,
+            ..zero_time_pom_config()
+        });
+        let (sender, receiver) = channel();
+        pom.register_receiver(receiver);
+        let pom_receiver = pom.chan_sender();
+        let handle = thread::spawn(move || {
+            sender.send(UIChannel::Proceed).unwrap();
+            let mut next_states = vec![];
+            while next_states.len() < 4 {
+                if let Ok(PomodoroChannel::Completed(next_state, _finished_pomodoros, _task)) =
+                    pom_receiver.recv()
+                {
+                    next_states.push(next_state);
+                    if next_states.len() < 4 {
+                        sender.send(UIChannel::Proceed).unwrap();
+                    }
+                }
+            }
+            sender.send(UIChannel::Cancel).unwrap();
+            next_states
+        });
+        pom.listen_loop();
+        let next_states = handle.join().unwrap();
+        assert_eq!(
+            next_states,
+            vec![
+                PomodoroStates::ShortBreak,
+                PomodoroStates::Pomodoro,
+                PomodoroStates::LongBreak,
+                PomodoroStates::Pomodoro,
+            ]
+        );
+        assert_eq!(pom.finished_pomodoros, 2);
+    }
+
+    #[test]
+    fn task_is_carried_to_the_ui_and_observers() {
+        create_mock! {
+            impl Observer for MockObserver (self) {
+                expect_callback("callback"):
+                fn callback(&self, event: IntervalEvent);
+            }
+        }
+        let mut observer = MockObserver::new();
+        observer
+            .expect_callback()
+            .called_once()
+            .with(passes(|event: &IntervalEvent| {
+                event.task == Some("TICKET-42".to_string())
+            }));
+        let mut pom = Pomodoro::new(zero_time_pom_config());
+        pom.add_observer(&observer);
+        let (sender, receiver) = channel();
+        pom.register_receiver(receiver);
+        let pom_receiver = pom.chan_sender();
+        let handle = thread::spawn(move || {
+            sender
+                .send(UIChannel::Task(Some("TICKET-42".to_string())))
+                .unwrap();
+            sender.send(UIChannel::Proceed).unwrap();
+            loop {
+                if let PomodoroChannel::Completed(_next_state, _finished_pomodoros, task) =
+                    pom_receiver.recv().unwrap()
+                {
+                    sender.send(UIChannel::Cancel).unwrap();
+                    return task;
+                }
+            }
+        });
+        pom.listen_loop();
+        assert_eq!(handle.join().unwrap(), Some("TICKET-42".to_string()));
+    }
+
+    #[test]
+    fn events_are_timed_with_the_clock() {
+        create_mock! {
+            impl Observer for MockObserver (self) {
+                expect_callback("callback"):
+                fn callback(&self, event: IntervalEvent);
+            }
+        }
+        let mut observer = MockObserver::new();
+        observer
+            .expect_callback()
+            .called_once()
+            .with(passes(|event: &IntervalEvent| {
+                event.started_at == Utc.ymd(2019, 7, 20).and_hms(12, 0, 0)
+                    && event.ended_at == Utc.ymd(2019, 7, 20).and_hms(12, 0, 20)
+                    && event.outcome == IntervalOutcome::Skipped
+            }));
+        let mut pom = Pomodoro::new(PomodoroConfig::default());
+        let clock = fake_clock();
+        pom.set_clock(clock.clone());
+        pom.add_observer(&observer);
+        let (sender, receiver) = channel();
+        pom.register_receiver(receiver);
+        let pom_receiver = pom.chan_sender();
+        let handle = thread::spawn(move || {
+            sender.send(UIChannel::Proceed).unwrap();
+            let mut updates = 0;
+            loop {
+                match pom_receiver.recv().unwrap() {
+                    PomodoroChannel::Update(_progress) => {
+                        updates += 1;
+                        if updates <= 20 {
+                            clock.advance(Duration::from_secs(1));
+                        } else {
+                            sender.send(UIChannel::Skip).unwrap();
+                        }
+                    }
+                    PomodoroChannel::Paused(_progress) => (),
+                    PomodoroChannel::DayStarted(_finished_pomodoros) => (),
+                    PomodoroChannel::Completed(_next_state, _finished_pomodoros, _task) => {
+                        sender.send(UIChannel::Cancel).unwrap();
+                        break;
+                    }
+                }
+            }
+        });
+        pom.listen_loop();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn a_full_cycle_runs_on_a_fake_clock() {
+        let mut pom = Pomodoro::new(PomodoroConfig {
+            tick_in_secs: 60_f32,
+            ..PomodoroConfig::default()
+        });
+        let clock = fake_clock();
+        pom.set_clock(clock.clone());
+        let (sender, receiver) = channel();
+        pom.register_receiver(receiver);
+        let pom_receiver = pom.chan_sender();
+        let handle = thread::spawn(move || {
+            sender.send(UIChannel::Proceed).unwrap();
+            let mut next_states = vec![];
+            loop {
+                match pom_receiver.recv().unwrap() {
+                    PomodoroChannel::Update(_progress) => {
+                        clock.advance(Duration::from_secs(60));
+                    }
+                    PomodoroChannel::Paused(_progress) => (),
+                    PomodoroChannel::DayStarted(_finished_pomodoros) => (),
+                    PomodoroChannel::Completed(next_state, _finished_pomodoros, _task) => {
+                        next_states.push(next_state);
+                        if next_states.len() < 8 {
+                            sender.send(UIChannel::Proceed).unwrap();
+                        } else {
+                            sender.send(UIChannel::Cancel).unwrap();
+                            return next_states;
+                        }
+                    }
+                }
+            }
+        });
+        pom.listen_loop();
+        let next_states = handle.join().unwrap();
+        assert_eq!(next_states[6], PomodoroStates::LongBreak);
+        assert_eq!(next_states[7], PomodoroStates::Pomodoro);
+        assert_eq!(pom.finished_pomodoros, 4);
+    }
+
+    fn minute_tick_pom_config() -> PomodoroConfig {
+        PomodoroConfig {
+            tick_in_secs: 60_f32,
+            ..PomodoroConfig::default()
+        }
+    }
+
+    fn run_until_completed_at(pom: &mut Pomodoro, clock: Arc<FakeClock>) -> (PomodoroStates, u32) {
+        let (sender, receiver) = channel();
+        pom.register_receiver(receiver);
+        let pom_receiver = pom.chan_sender();
+        let handle = thread::spawn(move || {
+            sender.send(UIChannel::Proceed).unwrap();
+            loop {
+                match pom_receiver.recv().unwrap() {
+                    PomodoroChannel::Update(_progress) => {
+                        clock.advance(Duration::from_secs(60));
+                    }
+                    PomodoroChannel::Paused(_progress) => (),
+                    PomodoroChannel::DayStarted(_finished_pomodoros) => (),
+                    PomodoroChannel::Completed(next_state, finished_pomodoros, _task) => {
+                        sender.send(UIChannel::Cancel).unwrap();
+                        return (next_state, finished_pomodoros);
+                    }
+                }
+            }
+        });
+        pom.listen_loop();
+        handle.join().unwrap()
+    }
+
+    #[test]
+    fn a_pomodoro_ending_after_midnight_counts_for_the_new_day() {
+        let clock = Arc::new(FakeClock::new(Utc.ymd(2019, 7, 20).and_hms(23, 50, 0)));
+        let mut pom = Pomodoro::continue_from(5, minute_tick_pom_config());
+        pom.set_clock(clock.clone());
+        pom.set_day_boundary(DayBoundary::new(Some(chrono_tz::UTC), 0));
+        let (next_state, finished_pomodoros) = run_until_completed_at(&mut pom, clock);
+        assert_eq!(next_state, PomodoroStates::ShortBreak);
+        assert_eq!(finished_pomodoros, 1);
+    }
+
+    #[test]
+    fn a_break_ending_after_midnight_resets_the_count() {
+        let clock = Arc::new(FakeClock::new(Utc.ymd(2019, 7, 20).and_hms(23, 58, 0)));
+        let mut pom = Pomodoro::continue_from(5, minute_tick_pom_config());
+        pom.set_clock(clock.clone());
+        pom.set_day_boundary(DayBoundary::new(Some(chrono_tz::UTC), 0));
+        pom.next_state = PomodoroStates::ShortBreak;
+        let (next_state, finished_pomodoros) = run_until_completed_at(&mut pom, clock);
+        assert_eq!(next_state, PomodoroStates::Pomodoro);
+        assert_eq!(finished_pomodoros, 0);
+    }
+
+    #[test]
+    fn the_count_is_kept_within_a_day() {
+        let clock = Arc::new(FakeClock::new(Utc.ymd(2019, 7, 20).and_hms(12, 0, 0)));
+        let mut pom = Pomodoro::continue_from(5, minute_tick_pom_config());
+        pom.set_clock(clock.clone());
+        pom.set_day_boundary(DayBoundary::new(Some(chrono_tz::UTC), 0));
+        let (_next_state, finished_pomodoros) = run_until_completed_at(&mut pom, clock);
+        assert_eq!(finished_pomodoros, 6);
+    }
+
+    #[test]
+    fn a_new_day_while_waiting_resets_the_count() {
+        let clock = Arc::new(FakeClock::new(Utc.ymd(2019, 7, 20).and_hms(23, 0, 0)));
+        let mut pom = Pomodoro::continue_from(5, minute_tick_pom_config());
+        pom.set_clock(clock.clone());
+        pom.set_day_boundary(DayBoundary::new(Some(chrono_tz::UTC), 0));
+        let (sender, receiver) = channel();
+        pom.register_receiver(receiver);
+        let pom_receiver = pom.chan_sender();
+        let handle = thread::spawn(move || {
+            clock.advance(Duration::from_secs(2 * 60 * 60));
+            let message = pom_receiver.recv().unwrap();
+            sender.send(UIChannel::Cancel).unwrap();
+            match message {
+                PomodoroChannel::DayStarted(finished_pomodoros) => Some(finished_pomodoros),
+                _ => None,
+            }
+        });
+        pom.listen_loop();
+        assert_eq!(handle.join().unwrap(), Some(0));
+        assert_eq!(pom.finished_pomodoros, 0);
     }
 }
@@ -2,4 +2,40 @@ pub struct PomodoroConfig {
     pub pomodoro_time_in_mins: f32,
     pub short_break_time_in_mins: f32,
     pub long_break_time_in_mins: f32,
+    pub pomodoros_before_long_break: u32,
+    /// How often the remaining time is sent to the user interface.
+    pub tick_in_secs: f32,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        PomodoroConfig {
+            pomodoro_time_in_mins: 25_f32,
+            short_break_time_in_mins: 5_f32,
+            long_break_time_in_mins: 15_f32,
+            pomodoros_before_long_break: 4,
+            tick_in_secs: 1_f32,
+        }
+    }
+}
+
+impl PomodoroConfig {
+    /// The longest interval, a day. Far longer ones would overflow the
+    /// deadline of the countdown.
+    pub const MAX_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+    /// A shorter tick would flood the user interface with updates.
+    pub const MIN_TICK_SECS: f64 = 0.1;
+    pub const MAX_TICK_SECS: f64 = 60_f64;
+
+    /// Whether an interval of `minutes` lasts at least a second, rounded to
+    /// whole seconds, and at most a day.
+    pub fn is_valid_minutes(minutes: f64) -> bool {
+        let seconds = (minutes * 60_f64).round();
+        seconds >= 1_f64 && seconds <= PomodoroConfig::MAX_INTERVAL_SECS as f64
+    }
+
+    pub fn is_valid_tick(seconds: f64) -> bool {
+        (PomodoroConfig::MIN_TICK_SECS..=PomodoroConfig::MAX_TICK_SECS).contains(&seconds)
+    }
 }
@@ -1,6 +1,19 @@
+use std::fmt;
+
 #[derive(PartialEq, Clone, Debug)]
 pub enum PomodoroStates {
     Pomodoro,
     ShortBreak,
     LongBreak,
 }
+
+impl fmt::Display for PomodoroStates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PomodoroStates::Pomodoro => "pomodoro",
+            PomodoroStates::ShortBreak => "short break",
+            PomodoroStates::LongBreak => "long break",
+        };
+        write!(f, "{}", name)
+    }
+}
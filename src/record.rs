@@ -1,30 +1,114 @@
-use crate::files::RecordFile;
+use crate::files::{RecordFile, RecordFormat, RecordWriter, Schema, Totals};
 use crate::observers::Observer;
-use crate::pomodoro_core::PomodoroStates;
+use crate::pomodoro_core::{IntervalEvent, IntervalOutcome, PomodoroStates};
+use crate::timing::{Clock, DayBoundary, SystemClock};
 use chrono::prelude::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
-use std::thread;
+
+/// How the record keeps the pomodoros of a day.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordMode {
+    /// One line per day, rewritten after every pomodoro. In the JSON Lines
+    /// format a line with the new count and the pomodoro is added instead.
+    #[default]
+    Summary,
+    /// One line per pomodoro, appended when it ends. The daily totals are
+    /// counted when the record is read.
+    Append,
+}
 
 pub struct Record {
     record_file: Arc<Mutex<dyn RecordFile>>,
+    mode: RecordMode,
+    format: RecordFormat,
+    daily_goal: Option<u32>,
+    clock: Arc<dyn Clock>,
+    day_boundary: DayBoundary,
+    writer: RecordWriter,
 }
 
 impl Record {
     pub fn new(record_file: Arc<Mutex<dyn RecordFile>>) -> Record {
-        Record { record_file }
+        Record {
+            writer: RecordWriter::new(record_file.clone(), "the record"),
+            record_file,
+            mode: RecordMode::default(),
+            format: RecordFormat::default(),
+            daily_goal: None,
+            clock: Arc::new(SystemClock::new()),
+            day_boundary: DayBoundary::default(),
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: RecordMode) {
+        self.mode = mode;
+    }
+
+    pub fn set_format(&mut self, format: RecordFormat) {
+        self.format = format;
+    }
+
+    pub fn set_day_boundary(&mut self, day_boundary: DayBoundary) {
+        self.day_boundary = day_boundary;
     }
 
-    pub fn initialize(&self) {
-        let headers =
-            self.construct_content_vec("Date".to_string(), "Number of pomodoros".to_string());
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// The goal is stored next to the day's count so that it can be compared
+    /// later on, even if the goal changes in the meantime.
+    pub fn set_daily_goal(&mut self, daily_goal: Option<u32>) {
+        self.daily_goal = daily_goal;
+    }
+
+    /// Creates the record, or checks and migrates an existing one.
+    pub fn initialize(&self) -> Result<(), Box<dyn Error>> {
         self.record_file
             .lock()
             .unwrap()
-            .open_or_create_with_headers(&headers);
+            .open_or_create(&Record::schema(self.mode, self.format))
+    }
+
+    /// The headers of every version of the record. New columns go to the end
+    /// of a new version. The daily record in the JSON Lines format tells the
+    /// pomodoro of every count, as its lines are never rewritten.
+    pub fn schema(mode: RecordMode, format: RecordFormat) -> Schema {
+        match mode {
+            RecordMode::Summary => {
+                let mut versions = vec![
+                    vec!["Date", "Number of pomodoros"],
+                    vec!["Date", "Number of pomodoros", "Daily goal"],
+                ];
+                if format == RecordFormat::Jsonl {
+                    let mut with_pomodoro = versions[1].clone();
+                    with_pomodoro.extend(vec!["State", "Started at", "Ended at", "Task"]);
+                    versions.push(with_pomodoro);
+                }
+                Schema::new("record", versions)
+            }
+            RecordMode::Append => Schema::new(
+                "pomodoros",
+                vec![vec!["Date", "Ended at", "Daily goal", "Task"]],
+            ),
+        }
     }
 
     pub fn no_of_finished_pomodoros_from_record(&self) -> Option<u32> {
+        if self.mode == RecordMode::Append {
+            let today = self.day_boundary.date_of(self.clock.now());
+            return self
+                .daily_totals()
+                .ok()?
+                .into_iter()
+                .find(|(date, _)| *date == today)
+                .map(|(_, total)| total);
+        }
         let locked_file = self.record_file.lock().unwrap();
         match locked_file.get_last_pomodoro_date_and_line_no() {
             Some((last_date, _line_no)) => {
@@ -38,72 +122,190 @@ impl Record {
         }
     }
 
-    fn process(&self, _next_state: PomodoroStates, finished_pomodoros: u32) {
-        match self.write_record(finished_pomodoros) {
-            Ok(()) => (),
-            Err(_) => println!("Error: There was an error while writing to the record."),
+    /// The number of pomodoros of every day in the record. Lines which cannot
+    /// be read are skipped.
+    pub fn daily_totals(&self) -> Result<Vec<(NaiveDate, u32)>, Box<dyn Error>> {
+        if let Some(totals) = self.totals()? {
+            return Ok(totals.daily);
+        }
+        match self.mode {
+            // A day with several lines counts with its highest count.
+            RecordMode::Summary => {
+                let mut totals = BTreeMap::new();
+                for (date, total) in self.read_column("Number of pomodoros", 1)? {
+                    if let Ok(total) = total.parse::<u32>() {
+                        let day_total = totals.entry(date).or_insert(0);
+                        *day_total = total.max(*day_total);
+                    }
+                }
+                Ok(totals.into_iter().collect())
+            }
+            RecordMode::Append => {
+                let mut totals = BTreeMap::new();
+                for (date, _) in self.read_column("Date", 0)? {
+                    *totals.entry(date).or_insert(0) += 1;
+                }
+                Ok(totals.into_iter().collect())
+            }
         }
     }
 
-    fn write_record(&self, finished_pomodoros: u32) -> Result<(), Box<Error>> {
-        let content_vec =
-            self.construct_content_vec(self.get_current_date(), finished_pomodoros.to_string());
-        match self
-            .record_file
+    /// The totals per day, week and month if the record file sums them up
+    /// itself, as the SQLite database does.
+    pub fn totals(&self) -> Result<Option<Totals>, Box<dyn Error>> {
+        let counted_column = match self.mode {
+            RecordMode::Summary => Some("Number of pomodoros"),
+            RecordMode::Append => None,
+        };
+        self.record_file
             .lock()
             .unwrap()
-            .get_last_pomodoro_date_and_line_no()
-        {
-            Some((last_date, line_pos)) => {
-                let record_file: Arc<Mutex<RecordFile>> = self.record_file.clone();
-                if last_date == self.get_current_date() {
-                    thread::spawn(move || {
-                        record_file
-                            .lock()
-                            .unwrap()
-                            .overwrite_record_in_pos_with(line_pos, content_vec)
-                            .expect("error");
-                    });
-                } else {
-                    thread::spawn(move || {
-                        record_file
-                            .lock()
-                            .unwrap()
-                            .write_record_to_new_line(content_vec)
-                            .expect("error");
-                    });
+            .totals(counted_column)
+            .transpose()
+    }
+
+    /// The daily goal of every day in the record which had one.
+    pub fn daily_goals(&self) -> Result<Vec<(NaiveDate, u32)>, Box<dyn Error>> {
+        // The goal at the last pomodoro of a day with several lines counts.
+        let goals: BTreeMap<_, _> = self
+            .read_column("Daily goal", 2)?
+            .into_iter()
+            .filter_map(|(date, goal)| goal.parse::<u32>().ok().map(|goal| (date, goal)))
+            .collect();
+        Ok(goals.into_iter().collect())
+    }
+
+    /// Writes one line per day with its total and goal, the format of the
+    /// summary record, to `file`.
+    pub fn export_summary(&self, file: &dyn RecordFile) -> Result<(), Box<dyn Error>> {
+        let goals: BTreeMap<_, _> = self.daily_goals()?.into_iter().collect();
+        file.write_headers(&Record::schema(RecordMode::Summary, RecordFormat::Csv).headers())?;
+        for (date, total) in self.daily_totals()? {
+            file.write_record_to_new_line(
+                self.construct_content_vec(
+                    date.format("%Y-%m-%d").to_string(),
+                    total.to_string(),
+                    goals
+                        .get(&date)
+                        .map(|goal| goal.to_string())
+                        .unwrap_or_default(),
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    // The date and the value in the column with the given header of every
+    // line with a valid date. Records without headers fall back to the
+    // column's position.
+    fn read_column(
+        &self,
+        header: &str,
+        fallback: usize,
+    ) -> Result<Vec<(NaiveDate, String)>, Box<dyn Error>> {
+        let record_file = self.record_file.lock().unwrap();
+        let headers = record_file.read_headers()?;
+        let column_of = |name: &str, fallback: usize| {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .unwrap_or(fallback)
+        };
+        let (date_column, value_column) = (column_of("Date", 0), column_of(header, fallback));
+        let mut values = Vec::new();
+        for record in record_file.read_records()? {
+            if let (Some(date), Some(value)) = (record.get(date_column), record.get(value_column)) {
+                if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                    values.push((date, value.clone()));
                 }
             }
-            None => {
-                let record_file: Arc<Mutex<RecordFile>> = self.record_file.clone();
-                thread::spawn(move || {
-                    record_file
-                        .lock()
-                        .unwrap()
-                        .write_record_to_new_line(content_vec)
-                        .expect("error");
-                });
-            }
         }
-        Ok(())
+        Ok(values)
+    }
+
+    /// Failed writes are sent here instead of being printed.
+    pub fn set_error_sender(&self, errors: Sender<String>) {
+        self.writer.set_error_sender(errors);
+    }
+
+    /// Waits until every write queued so far has reached the file. Called
+    /// before the app exits.
+    pub fn flush(&self) {
+        self.writer.flush();
     }
 
-    fn construct_content_vec(&self, first_str: String, sec_str: String) -> Vec<String> {
-        let mut vec = Vec::new();
-        vec.push(first_str.clone());
-        vec.push(sec_str.clone());
-        vec
+    // The day's count is read back from the file when the write happens, so
+    // that pomodoros of several running instances add up. Failed writes are
+    // reported by the writer.
+    fn process(&self, event: &IntervalEvent) {
+        // The date of the end of the interval, so that a pomodoro counted
+        // towards a new day is written to that day's line.
+        let date = self.get_date(event.ended_at);
+        let mut fields = BTreeMap::new();
+        fields.insert("Date", date.clone());
+        fields.insert(
+            "Daily goal",
+            self.daily_goal
+                .map(|goal| goal.to_string())
+                .unwrap_or_default(),
+        );
+        fields.insert("State", event.state.to_string());
+        fields.insert("Started at", event.started_at.to_rfc3339());
+        fields.insert("Ended at", event.ended_at.to_rfc3339());
+        fields.insert("Task", event.task.clone().unwrap_or_default());
+        let headers = Record::schema(self.mode, self.format).headers();
+        self.writer.increment(date, move |finished_pomodoros| {
+            headers
+                .iter()
+                .map(|header| match header.as_str() {
+                    "Number of pomodoros" => finished_pomodoros.to_string(),
+                    header => fields.get(header).cloned().unwrap_or_default(),
+                })
+                .collect()
+        })
+    }
+
+    fn append(&self, event: &IntervalEvent) {
+        let content_vec = vec![
+            self.get_date(event.ended_at),
+            event.ended_at.to_rfc3339(),
+            self.daily_goal
+                .map(|goal| goal.to_string())
+                .unwrap_or_default(),
+            event.task.clone().unwrap_or_default(),
+        ];
+        self.writer.append(content_vec);
+    }
+
+    fn construct_content_vec(
+        &self,
+        first_str: String,
+        sec_str: String,
+        third_str: String,
+    ) -> Vec<String> {
+        vec![first_str, sec_str, third_str]
     }
 
     fn get_current_date(&self) -> String {
-        Utc::now().format("%Y-%m-%d").to_string()
+        self.get_date(self.clock.now())
+    }
+
+    fn get_date(&self, time: DateTime<Utc>) -> String {
+        self.day_boundary
+            .date_of(time)
+            .format("%Y-%m-%d")
+            .to_string()
     }
 }
 
 impl Observer for Record {
-    fn callback(&self, next_state: PomodoroStates, finished_pomodoros: u32) {
-        if finished_pomodoros > 0 {
-            self.process(next_state, finished_pomodoros);
+    fn callback(&self, event: IntervalEvent) {
+        if event.state != PomodoroStates::Pomodoro || event.outcome != IntervalOutcome::Completed {
+            return;
+        }
+        match self.mode {
+            RecordMode::Append => self.append(&event),
+            RecordMode::Summary => self.process(&event),
         }
     }
 }
@@ -111,9 +313,70 @@ impl Observer for Record {
 #[cfg(test)]
 mod tests {
     use crate::files::nullfile::NullFile;
-    use crate::record::Record;
+    use crate::files::{JsonlFile, RecordFile, RecordFormat, Schema};
+    use crate::observers::Observer;
+    use crate::pomodoro_core::{IntervalEvent, IntervalOutcome, PomodoroStates};
+    use crate::record::{Record, RecordMode};
+    use crate::timing::fakeclock::FakeClock;
+    use crate::timing::DayBoundary;
     use chrono::prelude::*;
+    use serde_json::Value;
+    use std::error::Error;
+    use std::fs;
     use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    struct SlowFile {
+        written: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    impl RecordFile for SlowFile {
+        fn open_or_create(&self, _schema: &Schema) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+        fn write_headers(&self, _headers: &[String]) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+        fn write_record_to_new_line(&self, record: Vec<String>) -> Result<(), Box<dyn Error>> {
+            thread::sleep(Duration::from_millis(100));
+            self.written.lock().unwrap().push(record);
+            Ok(())
+        }
+        fn overwrite_record_in_pos_with(
+            &self,
+            _pos: usize,
+            record: Vec<String>,
+        ) -> Result<(), Box<dyn Error>> {
+            self.write_record_to_new_line(record)
+        }
+        fn read_records(&self) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+            Ok(self.written.lock().unwrap().clone())
+        }
+        fn read_headers(&self) -> Result<Vec<String>, Box<dyn Error>> {
+            Ok(Vec::new())
+        }
+        fn get_last_pomodoro_count(&self) -> Option<u32> {
+            None
+        }
+        fn get_last_pomodoro_date_and_line_no(&self) -> Option<(String, usize)> {
+            None
+        }
+    }
+
+    fn pomodoro_event(ended_at: DateTime<Utc>, outcome: IntervalOutcome) -> IntervalEvent {
+        IntervalEvent {
+            state: PomodoroStates::Pomodoro,
+            next_state: PomodoroStates::ShortBreak,
+            started_at: ended_at,
+            ended_at,
+            planned_secs: 0,
+            paused: Duration::from_secs(0),
+            outcome,
+            finished_pomodoros: 1,
+            task: None,
+        }
+    }
 
     #[test]
     fn test_last_pomodoro_not_from_today() {
@@ -122,10 +385,170 @@ mod tests {
         assert_eq!(record.no_of_finished_pomodoros_from_record(), None);
     }
 
+    #[test]
+    fn daily_totals_are_read_from_the_record() {
+        let null_file = NullFile::new(true, String::from("2019-07-20"));
+        let record = Record::new(Arc::new(Mutex::new(null_file)));
+        assert_eq!(
+            record.daily_totals().unwrap(),
+            vec![(NaiveDate::from_ymd(2019, 7, 20), 10)]
+        );
+    }
+
+    #[test]
+    fn daily_goals_are_read_from_the_record() {
+        let null_file = NullFile::new(true, String::from("2019-07-20"));
+        let record = Record::new(Arc::new(Mutex::new(null_file)));
+        assert_eq!(
+            record.daily_goals().unwrap(),
+            vec![(NaiveDate::from_ymd(2019, 7, 20), 12)]
+        );
+    }
+
     #[test]
     fn test_last_pomodoro_from_today() {
-        let null_file = NullFile::new(true, Utc::now().format("%Y-%m-%d").to_string());
+        let null_file = NullFile::new(true, Local::now().format("%Y-%m-%d").to_string());
         let record = Record::new(Arc::new(Mutex::new(null_file)));
         assert_eq!(record.no_of_finished_pomodoros_from_record(), Some(10));
     }
+
+    #[test]
+    fn the_count_starts_over_on_the_next_day() {
+        let null_file = NullFile::new(true, String::from("2019-07-20"));
+        let clock = Arc::new(FakeClock::new(Utc.ymd(2019, 7, 20).and_hms(23, 50, 0)));
+        let mut record = Record::new(Arc::new(Mutex::new(null_file)));
+        record.set_clock(clock.clone());
+        record.set_day_boundary(DayBoundary::new(Some(chrono_tz::UTC), 0));
+        assert_eq!(record.no_of_finished_pomodoros_from_record(), Some(10));
+        clock.advance(Duration::from_secs(10 * 60));
+        assert_eq!(record.no_of_finished_pomodoros_from_record(), None);
+    }
+
+    #[test]
+    fn the_day_follows_the_day_boundary() {
+        let null_file = NullFile::new(true, String::from("2019-07-20"));
+        let clock = Arc::new(FakeClock::new(Utc.ymd(2019, 7, 21).and_hms(1, 0, 0)));
+        let mut record = Record::new(Arc::new(Mutex::new(null_file)));
+        record.set_clock(clock.clone());
+        record.set_day_boundary(DayBoundary::new(Some(chrono_tz::UTC), 4));
+        assert_eq!(record.no_of_finished_pomodoros_from_record(), Some(10));
+        clock.advance(Duration::from_secs(3 * 60 * 60));
+        assert_eq!(record.no_of_finished_pomodoros_from_record(), None);
+    }
+
+    #[test]
+    fn flush_waits_for_pending_writes() {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let record = Record::new(Arc::new(Mutex::new(SlowFile {
+            written: written.clone(),
+        })));
+        record.callback(pomodoro_event(
+            Utc.ymd(2019, 7, 20).and_hms(12, 0, 0),
+            IntervalOutcome::Completed,
+        ));
+        record.flush();
+        assert_eq!(written.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn append_mode_counts_the_lines_of_a_day() {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let clock = Arc::new(FakeClock::new(Utc.ymd(2019, 7, 21).and_hms(12, 0, 0)));
+        let mut record = Record::new(Arc::new(Mutex::new(SlowFile {
+            written: written.clone(),
+        })));
+        record.set_mode(RecordMode::Append);
+        record.set_clock(clock);
+        record.set_day_boundary(DayBoundary::new(Some(chrono_tz::UTC), 0));
+        record.set_daily_goal(Some(8));
+        let day = Utc.ymd(2019, 7, 20).and_hms(10, 0, 0);
+        let next_day = Utc.ymd(2019, 7, 21).and_hms(10, 0, 0);
+        let mut with_task = pomodoro_event(day, IntervalOutcome::Completed);
+        with_task.task = Some("write, intro".to_string());
+        record.callback(with_task);
+        record.callback(pomodoro_event(day, IntervalOutcome::Skipped));
+        record.callback(pomodoro_event(day, IntervalOutcome::Aborted));
+        let mut short_break = pomodoro_event(day, IntervalOutcome::Completed);
+        short_break.state = PomodoroStates::ShortBreak;
+        record.callback(short_break);
+        record.callback(pomodoro_event(next_day, IntervalOutcome::Completed));
+        record.flush();
+        assert_eq!(written.lock().unwrap().len(), 2);
+        assert_eq!(written.lock().unwrap()[0][3], "write, intro");
+        assert_eq!(
+            record.daily_totals().unwrap(),
+            vec![
+                (NaiveDate::from_ymd(2019, 7, 20), 1),
+                (NaiveDate::from_ymd(2019, 7, 21), 1)
+            ]
+        );
+        assert_eq!(record.no_of_finished_pomodoros_from_record(), Some(1));
+
+        let exported = Arc::new(Mutex::new(Vec::new()));
+        record
+            .export_summary(&SlowFile {
+                written: exported.clone(),
+            })
+            .unwrap();
+        assert_eq!(
+            *exported.lock().unwrap(),
+            vec![
+                vec!["2019-07-20".to_string(), "1".to_string(), "8".to_string()],
+                vec!["2019-07-21".to_string(), "1".to_string(), "8".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn the_jsonl_record_adds_a_line_per_pomodoro() {
+        let location = std::env::temp_dir()
+            .join(format!("rp-record-jsonl-{}", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let schema = Record::schema(RecordMode::Summary, RecordFormat::Jsonl);
+        let file = JsonlFile::new(location.clone(), "record".to_string(), schema);
+        let clock = Arc::new(FakeClock::new(Utc.ymd(2019, 7, 21).and_hms(12, 0, 0)));
+        let mut record = Record::new(Arc::new(Mutex::new(file)));
+        record.set_format(RecordFormat::Jsonl);
+        record.set_clock(clock);
+        record.set_day_boundary(DayBoundary::new(Some(chrono_tz::UTC), 0));
+        record.set_daily_goal(Some(8));
+        record.initialize().unwrap();
+        let day = Utc.ymd(2019, 7, 20).and_hms(10, 0, 0);
+        let mut with_task = pomodoro_event(day, IntervalOutcome::Completed);
+        with_task.task = Some("intro".to_string());
+        record.callback(with_task);
+        record.callback(pomodoro_event(day, IntervalOutcome::Completed));
+        record.callback(pomodoro_event(
+            Utc.ymd(2019, 7, 21).and_hms(10, 0, 0),
+            IntervalOutcome::Completed,
+        ));
+        record.flush();
+        let contents = fs::read_to_string(format!("{}/record.jsonl", location));
+        let totals = record.daily_totals();
+        let today = record.no_of_finished_pomodoros_from_record();
+        fs::remove_dir_all(&location).unwrap();
+        let objects: Vec<Value> = contents
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(objects.len(), 3);
+        assert_eq!(
+            objects[0].to_string(),
+            "{\"date\":\"2019-07-20\",\"number_of_pomodoros\":1,\"daily_goal\":8,\
+             \"state\":\"pomodoro\",\"started_at\":\"2019-07-20T10:00:00+00:00\",\
+             \"ended_at\":\"2019-07-20T10:00:00+00:00\",\"task\":\"intro\"}"
+        );
+        assert_eq!(objects[1]["number_of_pomodoros"], 2);
+        assert_eq!(objects[2]["number_of_pomodoros"], 1);
+        assert_eq!(
+            totals.unwrap(),
+            vec![
+                (NaiveDate::from_ymd(2019, 7, 20), 2),
+                (NaiveDate::from_ymd(2019, 7, 21), 1)
+            ]
+        );
+        assert_eq!(today, Some(1));
+    }
 }
@@ -0,0 +1,359 @@
+use crate::files::Totals;
+use chrono::prelude::*;
+use chrono::Duration;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const HISTOGRAM_WIDTH: u32 = 40;
+const SHOWN_WEEKS: usize = 8;
+const SHOWN_MONTHS: usize = 12;
+/// The longest histogram, about ten years.
+pub const MAX_HISTOGRAM_DAYS: u32 = 3660;
+
+pub struct Statistics {
+    daily_totals: BTreeMap<NaiveDate, u32>,
+    daily_goals: BTreeMap<NaiveDate, u32>,
+    weekly_totals: Option<Vec<(i32, u32, u32)>>,
+    monthly_totals: Option<Vec<(i32, u32, u32)>>,
+    today: NaiveDate,
+}
+
+impl Statistics {
+    pub fn new(daily_totals: Vec<(NaiveDate, u32)>, today: NaiveDate) -> Statistics {
+        let mut totals = BTreeMap::new();
+        for (date, count) in daily_totals {
+            // A day should only appear once, but a record written by two
+            // instances at the same time may contain it twice.
+            let total = totals.entry(date).or_insert(0);
+            *total = count.max(*total);
+        }
+        Statistics {
+            daily_totals: totals,
+            daily_goals: BTreeMap::new(),
+            weekly_totals: None,
+            monthly_totals: None,
+            today,
+        }
+    }
+
+    /// Uses the totals of the record file, if it sums them up itself, instead
+    /// of adding up the daily totals.
+    pub fn from_totals(totals: Totals, today: NaiveDate) -> Statistics {
+        let mut statistics = Statistics::new(totals.daily, today);
+        statistics.weekly_totals = Some(totals.weekly);
+        statistics.monthly_totals = Some(totals.monthly);
+        statistics
+    }
+
+    /// Goals as written to the record; days without a goal are left out.
+    pub fn with_daily_goals(mut self, daily_goals: Vec<(NaiveDate, u32)>) -> Statistics {
+        self.daily_goals = daily_goals.into_iter().collect();
+        self
+    }
+
+    /// Days which had a goal as (reached, with a goal).
+    pub fn goals_reached(&self) -> (u32, u32) {
+        let reached = self
+            .daily_goals
+            .iter()
+            .filter(|(date, goal)| self.day_total(**date) >= **goal)
+            .count();
+        (reached as u32, self.daily_goals.len() as u32)
+    }
+
+    pub fn total(&self) -> u32 {
+        self.daily_totals.values().sum()
+    }
+
+    pub fn day_total(&self, date: NaiveDate) -> u32 {
+        *self.daily_totals.get(&date).unwrap_or(&0)
+    }
+
+    /// Totals per ISO week as (year, week, total), oldest first.
+    pub fn weekly_totals(&self) -> Vec<(i32, u32, u32)> {
+        if let Some(totals) = self.weekly_totals.as_ref() {
+            return totals.clone();
+        }
+        let mut totals: BTreeMap<(i32, u32), u32> = BTreeMap::new();
+        for (date, count) in self.daily_totals.iter() {
+            let week = date.iso_week();
+            *totals.entry((week.year(), week.week())).or_insert(0) += count;
+        }
+        totals
+            .into_iter()
+            .map(|((year, week), total)| (year, week, total))
+            .collect()
+    }
+
+    /// Totals per month as (year, month, total), oldest first.
+    pub fn monthly_totals(&self) -> Vec<(i32, u32, u32)> {
+        if let Some(totals) = self.monthly_totals.as_ref() {
+            return totals.clone();
+        }
+        let mut totals: BTreeMap<(i32, u32), u32> = BTreeMap::new();
+        for (date, count) in self.daily_totals.iter() {
+            *totals.entry((date.year(), date.month())).or_insert(0) += count;
+        }
+        totals
+            .into_iter()
+            .map(|((year, month), total)| (year, month, total))
+            .collect()
+    }
+
+    /// Average over the days with at least one pomodoro.
+    pub fn daily_average(&self) -> f64 {
+        let active_days = self
+            .daily_totals
+            .values()
+            .filter(|count| **count > 0)
+            .count();
+        if active_days == 0 {
+            0_f64
+        } else {
+            f64::from(self.total()) / active_days as f64
+        }
+    }
+
+    pub fn best_day(&self) -> Option<(NaiveDate, u32)> {
+        self.daily_totals
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .fold(None, |best, (date, count)| match best {
+                Some((_, best_count)) if best_count >= *count => best,
+                _ => Some((*date, *count)),
+            })
+    }
+
+    /// Consecutive days with at least `min_pomodoros` up to today. A today
+    /// which has not reached the minimum yet does not break the streak.
+    pub fn current_streak(&self, min_pomodoros: u32) -> u32 {
+        let mut day = self.today;
+        if self.day_total(day) < min_pomodoros {
+            day = day.pred();
+        }
+        let mut streak = 0;
+        while self.daily_totals.contains_key(&day) && self.day_total(day) >= min_pomodoros {
+            streak += 1;
+            day = day.pred();
+        }
+        streak
+    }
+
+    pub fn longest_streak(&self, min_pomodoros: u32) -> u32 {
+        let mut longest = 0;
+        let mut streak = 0;
+        let mut previous_day: Option<NaiveDate> = None;
+        for (date, count) in self.daily_totals.iter() {
+            if *count < min_pomodoros {
+                streak = 0;
+            } else if previous_day == Some(date.pred()) && streak > 0 {
+                streak += 1;
+            } else {
+                streak = 1;
+            }
+            previous_day = Some(*date);
+            longest = longest.max(streak);
+        }
+        longest
+    }
+
+    /// One line per day for the last `days` days, today included. Days before
+    /// the earliest date chrono knows are left out.
+    pub fn histogram(&self, days: u32) -> Vec<String> {
+        let dates: Vec<NaiveDate> = (0..i64::from(days))
+            .rev()
+            .filter_map(|days_ago| self.today.checked_sub_signed(Duration::days(days_ago)))
+            .collect();
+        let max_count = dates
+            .iter()
+            .map(|date| self.day_total(*date))
+            .max()
+            .unwrap_or(0);
+        dates
+            .iter()
+            .map(|date| {
+                let count = self.day_total(*date);
+                let bar_length = if max_count > HISTOGRAM_WIDTH {
+                    count * HISTOGRAM_WIDTH / max_count
+                } else {
+                    count
+                };
+                format!(
+                    "{} {:>3} {}",
+                    date.format("%Y-%m-%d %a"),
+                    count,
+                    "#".repeat(bar_length as usize)
+                )
+            })
+            .collect()
+    }
+
+    pub fn report(&self, min_pomodoros: u32, days: u32) -> String {
+        let mut report = String::new();
+        let _ = writeln!(report, "Total pomodoros: {}", self.total());
+        let _ = writeln!(report, "Today: {}", self.day_total(self.today));
+        let _ = writeln!(report, "Daily average: {:.1}", self.daily_average());
+        if let Some((date, count)) = self.best_day() {
+            let _ = writeln!(
+                report,
+                "Best day: {} with {}",
+                date.format("%Y-%m-%d"),
+                count
+            );
+        }
+        let _ = writeln!(
+            report,
+            "Current streak (at least {} a day): {} days",
+            min_pomodoros,
+            self.current_streak(min_pomodoros)
+        );
+        let _ = writeln!(
+            report,
+            "Longest streak (at least {} a day): {} days",
+            min_pomodoros,
+            self.longest_streak(min_pomodoros)
+        );
+        let (reached, with_goal) = self.goals_reached();
+        if with_goal > 0 {
+            let _ = writeln!(
+                report,
+                "Daily goal reached: {} of {} days",
+                reached, with_goal
+            );
+        }
+        let _ = writeln!(report, "\nWeeks:");
+        let weeks = self.weekly_totals();
+        for (year, week, total) in weeks.iter().skip(weeks.len().saturating_sub(SHOWN_WEEKS)) {
+            let _ = writeln!(report, "{}-W{:02} {:>4}", year, week, total);
+        }
+        let _ = writeln!(report, "\nMonths:");
+        let months = self.monthly_totals();
+        for (year, month, total) in months
+            .iter()
+            .skip(months.len().saturating_sub(SHOWN_MONTHS))
+        {
+            let _ = writeln!(report, "{}-{:02}   {:>4}", year, month, total);
+        }
+        let _ = writeln!(report, "\nLast {} days:", days);
+        for line in self.histogram(days) {
+            let _ = writeln!(report, "{}", line);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::Statistics;
+    use chrono::naive::MIN_DATE;
+    use chrono::NaiveDate;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2019, 7, day)
+    }
+
+    fn statistics() -> Statistics {
+        Statistics::new(
+            vec![
+                (date(1), 4),
+                (date(2), 8),
+                (date(3), 2),
+                (date(5), 6),
+                (date(6), 5),
+                (date(7), 3),
+                (date(8), 7),
+            ],
+            date(9),
+        )
+    }
+
+    #[test]
+    fn totals() {
+        let stats = statistics();
+        assert_eq!(stats.total(), 35);
+        assert_eq!(stats.weekly_totals(), vec![(2019, 27, 28), (2019, 28, 7)]);
+        assert_eq!(stats.monthly_totals(), vec![(2019, 7, 35)]);
+    }
+
+    #[test]
+    fn weeks_are_split_on_monday() {
+        let stats = Statistics::new(vec![(date(7), 3), (date(8), 4)], date(8));
+        assert_eq!(stats.weekly_totals(), vec![(2019, 27, 3), (2019, 28, 4)]);
+    }
+
+    #[test]
+    fn average_and_best_day() {
+        let stats = statistics();
+        assert_eq!(stats.daily_average(), 5_f64);
+        assert_eq!(stats.best_day(), Some((date(2), 8)));
+    }
+
+    #[test]
+    fn streaks() {
+        let stats = statistics();
+        assert_eq!(stats.current_streak(1), 4);
+        assert_eq!(stats.longest_streak(1), 4);
+        assert_eq!(stats.current_streak(5), 1);
+        assert_eq!(stats.longest_streak(4), 2);
+    }
+
+    #[test]
+    fn streak_ends_when_a_day_is_missed() {
+        let stats = Statistics::new(vec![(date(1), 4), (date(2), 8)], date(9));
+        assert_eq!(stats.current_streak(1), 0);
+        assert_eq!(stats.longest_streak(1), 2);
+    }
+
+    #[test]
+    fn duplicate_days_keep_the_highest_count() {
+        let stats = Statistics::new(vec![(date(1), 4), (date(1), 6)], date(1));
+        assert_eq!(stats.total(), 6);
+    }
+
+    #[test]
+    fn histogram_has_a_line_per_day() {
+        let stats = statistics();
+        assert_eq!(
+            stats.histogram(3),
+            vec![
+                "2019-07-07 Sun   3 ###",
+                "2019-07-08 Mon   7 #######",
+                "2019-07-09 Tue   0 ",
+            ]
+        );
+    }
+
+    #[test]
+    fn histogram_is_scaled_down_to_its_width() {
+        let stats = Statistics::new(vec![(date(1), 80), (date(2), 20)], date(2));
+        let histogram = stats.histogram(2);
+        assert_eq!(histogram[0].matches('#').count(), 40);
+        assert_eq!(histogram[1].matches('#').count(), 10);
+    }
+
+    #[test]
+    fn histogram_stops_at_the_earliest_date() {
+        let stats = Statistics::new(vec![], MIN_DATE.succ());
+        assert_eq!(stats.histogram(5).len(), 2);
+    }
+
+    #[test]
+    fn goals_reached() {
+        let stats = statistics().with_daily_goals(vec![(date(2), 6), (date(3), 6), (date(5), 6)]);
+        assert_eq!(stats.goals_reached(), (2, 3));
+        assert!(stats
+            .report(1, 7)
+            .contains("Daily goal reached: 2 of 3 days"));
+        assert!(!statistics().report(1, 7).contains("Daily goal"));
+    }
+
+    #[test]
+    fn empty_record() {
+        let stats = Statistics::new(vec![], date(9));
+        assert_eq!(stats.total(), 0);
+        assert_eq!(stats.daily_average(), 0_f64);
+        assert_eq!(stats.best_day(), None);
+        assert_eq!(stats.current_streak(1), 0);
+        assert_eq!(stats.longest_streak(1), 0);
+    }
+}
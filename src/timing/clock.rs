@@ -0,0 +1,20 @@
+use crate::communication::UIChannel;
+use chrono::prelude::*;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Source of time for everything that waits or looks at the date, so that
+/// tests can run a whole cycle without sleeping.
+pub trait Clock: Send + Sync {
+    /// Wall clock time, used for timestamps and the date in the record.
+    fn now(&self) -> DateTime<Utc>;
+    /// Monotonic time, used to measure intervals.
+    fn instant(&self) -> Instant;
+    fn sleep(&self, duration: Duration);
+    /// Waits for a message until the clock reaches `deadline`.
+    fn receive_until(
+        &self,
+        receiver: &Receiver<UIChannel>,
+        deadline: Instant,
+    ) -> Result<UIChannel, RecvTimeoutError>;
+}
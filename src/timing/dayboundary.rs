@@ -0,0 +1,67 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use chrono_tz::Tz;
+
+/// Decides which day a moment belongs to. Days follow the local time of the
+/// machine unless a time zone is given, and may start later than midnight
+/// for people who work into the night.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DayBoundary {
+    time_zone: Option<Tz>,
+    starts_at_hour: u32,
+}
+
+impl DayBoundary {
+    pub fn new(time_zone: Option<Tz>, starts_at_hour: u32) -> DayBoundary {
+        DayBoundary {
+            time_zone,
+            starts_at_hour,
+        }
+    }
+
+    pub fn date_of(&self, time: DateTime<Utc>) -> NaiveDate {
+        let local_time = match self.time_zone {
+            Some(time_zone) => time.with_timezone(&time_zone).naive_local(),
+            None => time.with_timezone(&Local).naive_local(),
+        };
+        (local_time - Duration::hours(i64::from(self.starts_at_hour))).date()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timing::DayBoundary;
+    use chrono::prelude::*;
+    use chrono_tz::Tz;
+
+    fn time_zone(name: &str) -> Option<Tz> {
+        Some(name.parse().unwrap())
+    }
+
+    #[test]
+    fn days_follow_the_time_zone() {
+        let day_boundary = DayBoundary::new(time_zone("America/New_York"), 0);
+        assert_eq!(
+            day_boundary.date_of(Utc.ymd(2019, 7, 21).and_hms(2, 0, 0)),
+            NaiveDate::from_ymd(2019, 7, 20)
+        );
+        let day_boundary = DayBoundary::new(time_zone("Asia/Tokyo"), 0);
+        assert_eq!(
+            day_boundary.date_of(Utc.ymd(2019, 7, 20).and_hms(16, 0, 0)),
+            NaiveDate::from_ymd(2019, 7, 21)
+        );
+    }
+
+    #[test]
+    fn a_day_can_start_after_midnight() {
+        let day_boundary = DayBoundary::new(time_zone("UTC"), 4);
+        assert_eq!(
+            day_boundary.date_of(Utc.ymd(2019, 7, 21).and_hms(3, 59, 59)),
+            NaiveDate::from_ymd(2019, 7, 20)
+        );
+        assert_eq!(
+            day_boundary.date_of(Utc.ymd(2019, 7, 21).and_hms(4, 0, 0)),
+            NaiveDate::from_ymd(2019, 7, 21)
+        );
+    }
+}
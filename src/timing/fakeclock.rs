@@ -0,0 +1,120 @@
+use crate::communication::UIChannel;
+use crate::timing::Clock;
+use chrono::prelude::*;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How often waiting threads look at the clock again.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A clock which only moves when `advance` is called. Waiting on it blocks
+/// until another thread advances it far enough.
+pub struct FakeClock {
+    started_at: DateTime<Utc>,
+    started_instant: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl FakeClock {
+    pub fn new(started_at: DateTime<Utc>) -> FakeClock {
+        FakeClock {
+            started_at,
+            started_instant: Instant::now(),
+            elapsed: Mutex::new(Duration::from_secs(0)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.started_at + chrono::Duration::from_std(self.elapsed()).unwrap()
+    }
+
+    fn instant(&self) -> Instant {
+        self.started_instant + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let deadline = self.instant() + duration;
+        while self.instant() < deadline {
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn receive_until(
+        &self,
+        receiver: &Receiver<UIChannel>,
+        deadline: Instant,
+    ) -> Result<UIChannel, RecvTimeoutError> {
+        loop {
+            match receiver.try_recv() {
+                Ok(message) => return Ok(message),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => (),
+            }
+            if self.instant() >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Err(RecvTimeoutError::Timeout) => (),
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::communication::UIChannel;
+    use crate::timing::fakeclock::FakeClock;
+    use crate::timing::Clock;
+    use chrono::prelude::*;
+    use std::sync::mpsc::{channel, RecvTimeoutError};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn time_only_moves_when_advanced() {
+        let clock = FakeClock::new(Utc.ymd(2019, 7, 20).and_hms(23, 59, 0));
+        let instant = clock.instant();
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now(), Utc.ymd(2019, 7, 21).and_hms(0, 0, 30));
+        assert_eq!(clock.instant() - instant, Duration::from_secs(90));
+    }
+
+    #[test]
+    fn receiving_times_out_once_the_deadline_is_reached() {
+        let clock = Arc::new(FakeClock::new(Utc.ymd(2019, 7, 20).and_hms(12, 0, 0)));
+        let (_sender, receiver) = channel::<UIChannel>();
+        let deadline = clock.instant() + Duration::from_secs(60);
+        let advancing_clock = clock.clone();
+        thread::spawn(move || advancing_clock.advance(Duration::from_secs(60)));
+        assert!(matches!(
+            clock.receive_until(&receiver, deadline),
+            Err(RecvTimeoutError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn messages_arrive_before_the_deadline() {
+        let clock = FakeClock::new(Utc.ymd(2019, 7, 20).and_hms(12, 0, 0));
+        let (sender, receiver) = channel();
+        sender.send(UIChannel::Pause).unwrap();
+        let deadline = clock.instant() + Duration::from_secs(60);
+        assert!(matches!(
+            clock.receive_until(&receiver, deadline),
+            Ok(UIChannel::Pause)
+        ));
+    }
+}
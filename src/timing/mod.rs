@@ -0,0 +1,10 @@
+mod clock;
+mod dayboundary;
+mod systemclock;
+
+pub use clock::Clock;
+pub use dayboundary::DayBoundary;
+pub use systemclock::SystemClock;
+
+#[cfg(test)]
+pub mod fakeclock;
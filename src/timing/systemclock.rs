@@ -0,0 +1,37 @@
+use crate::communication::UIChannel;
+use crate::timing::Clock;
+use chrono::prelude::*;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+
+    fn receive_until(
+        &self,
+        receiver: &Receiver<UIChannel>,
+        deadline: Instant,
+    ) -> Result<UIChannel, RecvTimeoutError> {
+        receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+    }
+}
@@ -0,0 +1,10 @@
+/// A key press, as far as the user interface cares about it.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Key {
+    Enter,
+    Backspace,
+    Esc,
+    /// Ctrl+C, which does not raise a signal while the terminal is in raw mode.
+    Interrupt,
+    Char(char),
+}
@@ -1,5 +1,8 @@
+mod key;
 mod output;
+mod progressscreen;
 mod simpletui;
 
+pub use key::Key;
 pub use output::Output;
 pub use simpletui::SimpleTUI;
@@ -1,5 +1,8 @@
+use crate::ui::Key;
 use crate::uimessages::UIMessages;
 
 pub trait Output: Sync + Send {
     fn display(&self, ui_message: UIMessages) -> ();
+    /// Blocks until a key is pressed. None means that no more keys can be read.
+    fn read_key(&self) -> Option<Key>;
 }
@@ -0,0 +1,126 @@
+use crate::uimessages::ProgressView;
+
+const MAX_BAR_WIDTH: usize = 60;
+
+/// The lines of the progress display, fitted into `width` columns.
+pub fn progress_lines(view: &ProgressView, paused: bool, width: usize) -> Vec<String> {
+    let progress = &view.progress;
+    let mut title = capitalized(&progress.state.to_string());
+    if paused {
+        title.push_str(" (paused)");
+    }
+    if let Some(task) = view.task.as_ref() {
+        title.push_str(" - ");
+        title.push_str(task);
+    }
+    let percent = (progress.fraction() * 100_f64).floor();
+    let bar_width = width.saturating_sub(7).clamp(10, MAX_BAR_WIDTH);
+    let today = match view.daily_goal {
+        Some(goal) => format!("{}/{}", view.finished_pomodoros, goal),
+        None => view.finished_pomodoros.to_string(),
+    };
+    vec![
+        title,
+        format!(
+            "{} {:>3}%",
+            progress_bar(progress.fraction(), bar_width),
+            percent
+        ),
+        format!(
+            "{} remaining of {}",
+            mm_ss(progress.remaining_secs()),
+            mm_ss(progress.total_secs)
+        ),
+        format!(
+            "Today: {} pomodoros    Next: {}",
+            today, progress.next_state
+        ),
+        String::new(),
+        if paused {
+            "space resume   s skip   a abort   q quit".to_string()
+        } else {
+            "space pause   s skip   a abort   q quit".to_string()
+        },
+    ]
+}
+
+pub fn progress_bar(fraction: f64, width: usize) -> String {
+    let inner_width = width.saturating_sub(2);
+    let filled = (fraction.clamp(0_f64, 1_f64) * inner_width as f64).round() as usize;
+    format!(
+        "[{}{}]",
+        "#".repeat(filled),
+        "-".repeat(inner_width - filled)
+    )
+}
+
+pub fn mm_ss(secs: u64) -> String {
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pomodoro_core::{IntervalProgress, PomodoroStates};
+    use crate::ui::progressscreen::*;
+    use crate::uimessages::ProgressView;
+
+    fn view() -> ProgressView {
+        ProgressView {
+            progress: IntervalProgress {
+                state: PomodoroStates::Pomodoro,
+                next_state: PomodoroStates::ShortBreak,
+                elapsed_secs: 600,
+                total_secs: 1500,
+            },
+            finished_pomodoros: 6,
+            daily_goal: Some(10),
+            task: Some("TICKET-42".to_string()),
+        }
+    }
+
+    #[test]
+    fn remaining_time_is_shown_as_minutes_and_seconds() {
+        assert_eq!(mm_ss(0), "00:00");
+        assert_eq!(mm_ss(59), "00:59");
+        assert_eq!(mm_ss(1500), "25:00");
+        assert_eq!(mm_ss(6001), "100:01");
+    }
+
+    #[test]
+    fn the_bar_fills_up_with_the_interval() {
+        assert_eq!(progress_bar(0_f64, 12), "[----------]");
+        assert_eq!(progress_bar(0.4, 12), "[####------]");
+        assert_eq!(progress_bar(1_f64, 12), "[##########]");
+    }
+
+    #[test]
+    fn progress_lines() {
+        assert_eq!(
+            super::progress_lines(&view(), false, 27),
+            vec![
+                "Pomodoro - TICKET-42",
+                "[#######-----------]  40%",
+                "15:00 remaining of 25:00",
+                "Today: 6/10 pomodoros    Next: short break",
+                "",
+                "space pause   s skip   a abort   q quit",
+            ]
+        );
+    }
+
+    #[test]
+    fn paused_intervals_are_marked() {
+        let lines = super::progress_lines(&view(), true, 80);
+        assert_eq!(lines[0], "Pomodoro (paused) - TICKET-42");
+        assert_eq!(lines[1].len(), 60 + 5);
+        assert!(lines[5].starts_with("space resume"));
+    }
+}
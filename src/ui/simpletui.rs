@@ -1,34 +1,100 @@
-use crate::ui::Output;
-use crate::uimessages::UIMessages;
-use crossterm::{terminal, ClearType, Color, Colored, Terminal};
-use std::io::{stdin, stdout, Write};
-use std::thread;
-use std::time::Duration;
+use crate::ui::progressscreen::progress_lines;
+use crate::ui::{Key, Output};
+use crate::uimessages::{ProgressView, UIMessages};
+use crossterm::{
+    cursor, input, terminal, ClearType, Color, Colored, InputEvent, KeyEvent, RawScreen,
+    SyncReader, Terminal,
+};
+use std::io::{self, stdout, Write};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
 pub struct SimpleTUI {
     terminal: Terminal,
+    colors: bool,
+    bell: bool,
+    showing_progress: AtomicBool,
+    keys: Mutex<Option<SyncReader>>,
+    // Shown below the progress display until the next summary.
+    error: Mutex<Option<String>>,
 }
 
 impl SimpleTUI {
     pub fn new() -> SimpleTUI {
+        SimpleTUI::with_options(true, false)
+    }
+
+    pub fn with_options(colors: bool, bell: bool) -> SimpleTUI {
         SimpleTUI {
             terminal: terminal(),
+            colors,
+            bell,
+            showing_progress: AtomicBool::new(false),
+            keys: Mutex::new(None),
+            error: Mutex::new(None),
         }
     }
 
-    fn play_animation(&self, ui_message: String) {
-        let mut frame: usize = 0;
-        let animation = vec!["| ", "/ ", "- ", "\\ ", ". "];
-        while frame < 5 {
-            self.terminal.clear(ClearType::CurrentLine).unwrap();
-            print!("\r");
-            self.print_styled_message(animation[frame], Colored::Fg(Color::Yellow));
-            self.print_styled_message(ui_message.as_str(), Colored::Fg(Color::Cyan));
+    /// Puts the terminal into raw mode so that single key presses can be
+    /// read. `restore` undoes it, as does a panic anywhere in the app.
+    pub fn enter_raw_mode(&self) -> io::Result<()> {
+        RawScreen::into_raw_mode()?.disable_drop();
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            default_hook(info);
+        }));
+        Ok(())
+    }
+
+    pub fn restore(&self) {
+        restore_terminal();
+    }
+
+    fn ring_bell(&self) {
+        if self.bell {
+            print!("\x07");
             stdout().flush().unwrap();
-            if frame < 4 {
-                thread::sleep(Duration::from_secs(1));
-            }
-            frame += 1;
+        }
+    }
+
+    // The progress display takes the whole screen and is redrawn in place on
+    // every update.
+    fn print_progress(&self, view: ProgressView, paused: bool) {
+        let cursor = cursor();
+        if !self.showing_progress.swap(true, Ordering::Relaxed) {
+            self.terminal.clear(ClearType::All).unwrap();
+            let _ = cursor.hide();
+        }
+        let (width, _height) = self.terminal.terminal_size();
+        for (row, line) in progress_lines(&view, paused, width as usize)
+            .iter()
+            .enumerate()
+        {
+            let _ = cursor.goto(0, row as u16);
+            self.terminal.clear(ClearType::CurrentLine).unwrap();
+            let color = match row {
+                0 => Color::White,
+                1 if paused => Color::DarkYellow,
+                1 => Color::Yellow,
+                5 => Color::AnsiValue(8),
+                _ => Color::Cyan,
+            };
+            self.print_styled_message(line.as_str(), Colored::Fg(color));
+        }
+        if let Some(error) = self.error.lock().unwrap().as_ref() {
+            let _ = cursor.goto(0, 7);
+            self.terminal.clear(ClearType::CurrentLine).unwrap();
+            self.print_styled_message(error.as_str(), Colored::Fg(Color::Red));
+        }
+        stdout().flush().unwrap();
+    }
+
+    fn leave_progress(&self) {
+        if self.showing_progress.swap(false, Ordering::Relaxed) {
+            let _ = cursor().show();
+            print!("\r\n");
         }
     }
 
@@ -37,35 +103,97 @@ impl SimpleTUI {
     }
 
     fn print_summary_message(&self, summary_message: String) {
+        self.error.lock().unwrap().take();
         self.terminal.clear(ClearType::All).unwrap();
         self.new_line_styled_message(summary_message.as_str(), Colored::Fg(Color::White));
     }
 
-    fn ask_for_input(&self) {
-        let mut s = String::new();
-        self.print_styled_message(" Please press enter...", Colored::Fg(Color::White));
-        let _ = stdout().flush();
-        stdin().read_line(&mut s).unwrap();
+    fn print_error_message(&self, error: String) {
+        if !self.showing_progress.load(Ordering::Relaxed) {
+            self.new_line_styled_message(error.as_str(), Colored::Fg(Color::Red));
+        }
+        *self.error.lock().unwrap() = Some(error);
+    }
+
+    fn print_input_message(&self) {
+        self.print_styled_message(" Press enter to continue...", Colored::Fg(Color::White));
+        stdout().flush().unwrap();
+    }
+
+    // Redrawn on every key press, so that the line shows what has been typed.
+    fn print_task_prompt(&self, current_task: Option<String>, typed: String) {
+        self.terminal.clear(ClearType::CurrentLine).unwrap();
+        print!("\r");
+        let prompt = match current_task {
+            Some(task) => format!("Task (enter keeps \"{}\", - for none): ", task),
+            None => "Task (enter for none): ".to_string(),
+        };
+        self.print_styled_message(prompt.as_str(), Colored::Fg(Color::White));
+        self.print_styled_message(typed.as_str(), Colored::Fg(Color::Cyan));
+        stdout().flush().unwrap();
+    }
+
+    fn next_key(&self) -> Option<Key> {
+        let mut keys = self.keys.lock().unwrap();
+        let keys = keys.get_or_insert_with(|| input().read_sync());
+        loop {
+            let key = match keys.next()? {
+                InputEvent::Keyboard(KeyEvent::Char('\n')) => Key::Enter,
+                InputEvent::Keyboard(KeyEvent::Char(c)) => Key::Char(c),
+                InputEvent::Keyboard(KeyEvent::Backspace) => Key::Backspace,
+                InputEvent::Keyboard(KeyEvent::Esc) => Key::Esc,
+                InputEvent::Keyboard(KeyEvent::Ctrl('c')) => Key::Interrupt,
+                _ => continue,
+            };
+            return Some(key);
+        }
     }
 
     fn print_styled_message(&self, message: &str, style: Colored) {
-        print!("{}{}", style, message);
+        if self.colors {
+            print!("{}{}", style, message);
+        } else {
+            print!("{}", message);
+        }
     }
 
+    // Raw mode does not return the carriage on a line feed.
     fn new_line_styled_message(&self, message: &str, style: Colored) {
-        println!("{}{}", style, message);
+        if self.colors {
+            print!("{}{}\r\n", style, message);
+        } else {
+            print!("{}\r\n", message);
+        }
     }
 }
 
 impl Output for SimpleTUI {
     fn display(&self, ui_message: UIMessages) {
+        if !matches!(
+            ui_message,
+            UIMessages::ProgressMessage(_)
+                | UIMessages::PauseMessage(_)
+                | UIMessages::ErrorMessage(_)
+        ) {
+            self.leave_progress();
+        }
         match ui_message {
             UIMessages::StateMessage(message) => self.print_state_message(message),
-            UIMessages::InputMessage() => self.ask_for_input(),
+            UIMessages::InputMessage() => self.print_input_message(),
             UIMessages::SummaryMessage(message) => self.print_summary_message(message),
-            UIMessages::ProgressMessage(message) => self.play_animation(message),
+            UIMessages::ProgressMessage(view) => self.print_progress(view, false),
+            UIMessages::PauseMessage(view) => self.print_progress(view, true),
+            UIMessages::AlertMessage() => self.ring_bell(),
+            UIMessages::ErrorMessage(error) => self.print_error_message(error),
+            UIMessages::TaskPrompt(current_task, typed) => {
+                self.print_task_prompt(current_task, typed)
+            }
         }
     }
+
+    fn read_key(&self) -> Option<Key> {
+        self.next_key()
+    }
 }
 
 impl Default for SimpleTUI {
@@ -73,3 +201,9 @@ impl Default for SimpleTUI {
         SimpleTUI::new()
     }
 }
+
+fn restore_terminal() {
+    let _ = RawScreen::disable_raw_mode();
+    let _ = cursor().show();
+    println!();
+}
@@ -1,6 +1,22 @@
+use crate::pomodoro_core::IntervalProgress;
+
 pub enum UIMessages {
     SummaryMessage(String),
     InputMessage(),
-    ProgressMessage(String),
+    ProgressMessage(ProgressView),
+    PauseMessage(ProgressView),
+    AlertMessage(),
     StateMessage(String),
+    /// The current task and what has been typed for the next one so far.
+    TaskPrompt(Option<String>, String),
+    ErrorMessage(String),
+}
+
+/// Everything the progress display shows about the running interval.
+#[derive(PartialEq, Clone, Debug)]
+pub struct ProgressView {
+    pub progress: IntervalProgress,
+    pub finished_pomodoros: u32,
+    pub daily_goal: Option<u32>,
+    pub task: Option<String>,
 }
@@ -1,35 +1,121 @@
 use crate::communication::*;
-use crate::pomodoro_core::PomodoroStates;
-use crate::ui::Output;
-use crate::uimessages::UIMessages;
+use crate::pomodoro_core::{IntervalProgress, PomodoroStates};
+use crate::ui::{Key, Output};
+use crate::uimessages::{ProgressView, UIMessages};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
+use std::thread;
+
+// Key presses and messages from the pomodoro are handled one at a time, in
+// the order in which they arrive.
+enum UIEvent {
+    Pomodoro(PomodoroChannel),
+    Key(Key),
+    Error(String),
+}
+
+#[derive(PartialEq, Clone, Debug)]
+enum Mode {
+    Waiting(PomodoroStates),
+    Running,
+    Paused,
+    Quit,
+}
 
 pub struct UserInterface {
     ui_sender: Option<Sender<UIChannel>>,
-    pom_receiver: Option<Receiver<PomodoroChannel>>,
+    events_sender: Sender<UIEvent>,
+    events: Receiver<UIEvent>,
     output: Arc<dyn Output>,
+    mode: Mode,
+    task: Option<String>,
+    typed_task: String,
+    daily_goal: Option<u32>,
+    finished_pomodoros: u32,
 }
 
 impl UserInterface {
     pub fn new(output: Arc<dyn Output>) -> UserInterface {
+        let (events_sender, events) = channel();
         UserInterface {
             ui_sender: None,
-            pom_receiver: None,
+            events_sender,
+            events,
             output,
+            mode: Mode::Waiting(PomodoroStates::Pomodoro),
+            task: None,
+            typed_task: String::new(),
+            daily_goal: None,
+            finished_pomodoros: 0,
+        }
+    }
+
+    pub fn set_task(&mut self, task: Option<String>) {
+        self.task = task;
+    }
+
+    pub fn set_daily_goal(&mut self, daily_goal: Option<u32>) {
+        self.daily_goal = daily_goal;
+    }
+
+    /// Quits the same way as Ctrl+C, for use from a signal handler.
+    pub fn interrupter(&self) -> impl Fn() + Send + 'static {
+        let events = self.events_sender.clone();
+        move || {
+            let _ = events.send(UIEvent::Key(Key::Interrupt));
         }
     }
 
+    /// Errors sent here are shown to the user, e.g. failed record writes.
+    pub fn error_sender(&self) -> Sender<String> {
+        let (sender, receiver) = channel();
+        let events = self.events_sender.clone();
+        thread::spawn(move || {
+            for error in receiver.iter() {
+                if events.send(UIEvent::Error(error)).is_err() {
+                    break;
+                }
+            }
+        });
+        sender
+    }
+
+    /// Runs until the user quits.
     pub fn start(&mut self, finished_pomodoros: u32) {
+        self.listen_for_keys();
         self.ask_for_ack(PomodoroStates::Pomodoro, finished_pomodoros);
+        while self.mode != Mode::Quit {
+            match self.events.recv() {
+                Ok(UIEvent::Pomodoro(message)) => self.handle_message(message),
+                Ok(UIEvent::Key(key)) => self.handle_key(key),
+                Ok(UIEvent::Error(error)) => self.output.display(UIMessages::ErrorMessage(error)),
+                Err(_) => break,
+            }
+        }
+    }
+
+    fn listen_for_keys(&self) {
+        let output = self.output.clone();
+        let events = self.events_sender.clone();
+        thread::spawn(move || {
+            while let Some(key) = output.read_key() {
+                if events.send(UIEvent::Key(key)).is_err() {
+                    break;
+                }
+            }
+        });
     }
 
     fn ask_for_ack(&mut self, next_state: PomodoroStates, finished_pomodoros: u32) {
+        self.finished_pomodoros = finished_pomodoros;
+        self.mode = Mode::Waiting(next_state.clone());
         self.print_finished_pomodoro_str(finished_pomodoros);
         if next_state == PomodoroStates::Pomodoro {
             self.output.display(UIMessages::StateMessage(
                 "Starting a new pomodoro.".to_string(),
             ));
+            self.output.display(UIMessages::StateMessage(String::new()));
+            self.show_task_prompt();
         } else if next_state == PomodoroStates::ShortBreak {
             self.output.display(UIMessages::StateMessage(
                 "Let's have a short break.".to_string(),
@@ -39,56 +125,127 @@ impl UserInterface {
                 "Let's have a long break.".to_string(),
             ));
         }
-        self.wait_for_user_input();
-        if let Some(channel) = self.ui_sender.as_ref() {
-            channel.send(UIChannel::Proceed).unwrap();
+        if next_state != PomodoroStates::Pomodoro {
+            self.output.display(UIMessages::InputMessage());
         }
-        self.listening_loop();
     }
 
-    fn wait_for_user_input(&self) {
-        self.output.display(UIMessages::InputMessage());
+    fn handle_key(&mut self, key: Key) {
+        match (self.mode.clone(), key) {
+            (_, Key::Interrupt) | (_, Key::Esc) => self.quit(),
+            (Mode::Waiting(PomodoroStates::Pomodoro), key) => self.edit_task(key),
+            (Mode::Waiting(_), Key::Enter) => self.proceed(),
+            (Mode::Running, Key::Char(' ')) => self.send(UIChannel::Pause),
+            (Mode::Paused, Key::Char(' ')) => self.send(UIChannel::Resume),
+            (Mode::Running, Key::Char('s')) | (Mode::Paused, Key::Char('s')) => {
+                self.send(UIChannel::Skip)
+            }
+            (Mode::Running, Key::Char('a')) | (Mode::Paused, Key::Char('a')) => {
+                self.send(UIChannel::Abort)
+            }
+            (_, Key::Char('q')) => self.quit(),
+            _ => (),
+        }
     }
 
-    fn play_animation(&mut self, remaining_secs: u64) {
-        if remaining_secs > 60 {
-            self.output.display(UIMessages::ProgressMessage(format!(
-                "{} minutes remaining",
-                self.remaining_minutes(remaining_secs)
-            )));
-        } else {
-            self.output.display(UIMessages::ProgressMessage(format!(
-                "{} seconds remaining",
-                remaining_secs
-            )));
+    // An empty answer keeps the current task, "-" clears it.
+    fn edit_task(&mut self, key: Key) {
+        match key {
+            Key::Enter => {
+                match self.typed_task.trim() {
+                    "" => (),
+                    "-" => self.task = None,
+                    task => self.task = Some(task.to_string()),
+                }
+                self.typed_task.clear();
+                self.send(UIChannel::Task(self.task.clone()));
+                self.proceed();
+                return;
+            }
+            Key::Backspace => {
+                self.typed_task.pop();
+            }
+            Key::Char(c) if !c.is_control() => self.typed_task.push(c),
+            _ => return,
         }
-        self.listening_loop();
+        self.show_task_prompt();
+    }
+
+    fn show_task_prompt(&self) {
+        self.output.display(UIMessages::TaskPrompt(
+            self.task.clone(),
+            self.typed_task.clone(),
+        ));
+    }
+
+    fn proceed(&mut self) {
+        self.mode = Mode::Running;
+        self.send(UIChannel::Proceed);
+    }
+
+    fn quit(&mut self) {
+        self.mode = Mode::Quit;
+        self.send(UIChannel::Cancel);
     }
 
-    fn remaining_minutes(&self, remaining_secs: u64) -> u64 {
-        (remaining_secs as f64 / f64::from(60)).ceil() as u64
+    fn send(&self, message: UIChannel) {
+        if let Some(channel) = self.ui_sender.as_ref() {
+            let _ = channel.send(message);
+        }
     }
 
-    fn listening_loop(&mut self) {
-        if let Some(channel) = self.pom_receiver.as_ref() {
-            if let Ok(message) = channel.recv() {
-                match message {
-                    PomodoroChannel::Update(remaining_secs) => self.play_animation(remaining_secs),
-                    PomodoroChannel::Completed(next_state, finished_pomodoros) => {
-                        self.ask_for_ack(next_state, finished_pomodoros)
-                    }
+    fn handle_message(&mut self, message: PomodoroChannel) {
+        match message {
+            PomodoroChannel::Update(progress) => {
+                self.mode = Mode::Running;
+                self.output
+                    .display(UIMessages::ProgressMessage(self.progress_view(progress)));
+            }
+            PomodoroChannel::Paused(progress) => {
+                self.mode = Mode::Paused;
+                self.output
+                    .display(UIMessages::PauseMessage(self.progress_view(progress)));
+            }
+            PomodoroChannel::Completed(next_state, finished_pomodoros, task) => {
+                self.task = task;
+                self.output.display(UIMessages::AlertMessage());
+                self.ask_for_ack(next_state, finished_pomodoros);
+            }
+            PomodoroChannel::DayStarted(finished_pomodoros) => {
+                self.finished_pomodoros = finished_pomodoros;
+                if let Mode::Waiting(next_state) = self.mode.clone() {
+                    self.ask_for_ack(next_state, finished_pomodoros);
                 }
             }
         }
     }
 
+    fn progress_view(&self, progress: IntervalProgress) -> ProgressView {
+        ProgressView {
+            progress,
+            finished_pomodoros: self.finished_pomodoros,
+            daily_goal: self.daily_goal,
+            task: self.task.clone(),
+        }
+    }
+
     fn print_finished_pomodoro_str(&self, finished: u32) {
-        let mut finished_string = String::new();
-        finished_string.push_str("You have finished ");
-        finished_string.push_str(&finished.to_string());
-        finished_string.push_str(" pomodoros today.");
         self.output
-            .display(UIMessages::SummaryMessage(finished_string));
+            .display(UIMessages::SummaryMessage(finished_pomodoro_str(
+                finished,
+                self.daily_goal,
+            )));
+    }
+}
+
+fn finished_pomodoro_str(finished: u32, daily_goal: Option<u32>) -> String {
+    match daily_goal {
+        Some(goal) if finished >= goal => format!(
+            "You have finished {} pomodoros today, your goal of {} is reached.",
+            finished, goal
+        ),
+        Some(goal) => format!("You have finished {}/{} pomodoros today.", finished, goal),
+        None => format!("You have finished {} pomodoros today.", finished),
     }
 }
 
@@ -102,6 +259,195 @@ impl ConcSender<UIChannel> for UserInterface {
 
 impl ConcReceiver<PomodoroChannel> for UserInterface {
     fn register_receiver(&mut self, receiver: Receiver<PomodoroChannel>) {
-        self.pom_receiver = Some(receiver);
+        let events = self.events_sender.clone();
+        thread::spawn(move || {
+            for message in receiver.iter() {
+                if events.send(UIEvent::Pomodoro(message)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::communication::*;
+    use crate::pomodoro_core::{IntervalProgress, PomodoroStates};
+    use crate::ui::{Key, Output};
+    use crate::uimessages::UIMessages;
+    use crate::userinterface::{finished_pomodoro_str, Mode, UIEvent, UserInterface};
+    use std::sync::mpsc::Receiver;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct RecordingOutput {
+        prompts: Mutex<Vec<String>>,
+        summaries: Mutex<Vec<String>>,
+    }
+
+    impl Output for RecordingOutput {
+        fn display(&self, ui_message: UIMessages) {
+            match ui_message {
+                UIMessages::TaskPrompt(_, typed) => self.prompts.lock().unwrap().push(typed),
+                UIMessages::SummaryMessage(summary) => self.summaries.lock().unwrap().push(summary),
+                _ => (),
+            }
+        }
+
+        fn read_key(&self) -> Option<Key> {
+            None
+        }
+    }
+
+    fn ui_with_output() -> (UserInterface, Receiver<UIChannel>, Arc<RecordingOutput>) {
+        let output = Arc::new(RecordingOutput::default());
+        let mut ui = UserInterface::new(output.clone());
+        let receiver = ui.chan_sender();
+        (ui, receiver, output)
+    }
+
+    fn progress(state: PomodoroStates) -> IntervalProgress {
+        IntervalProgress {
+            state,
+            next_state: PomodoroStates::ShortBreak,
+            elapsed_secs: 10,
+            total_secs: 60,
+        }
+    }
+
+    fn type_keys(ui: &mut UserInterface, text: &str) {
+        for c in text.chars() {
+            ui.handle_key(Key::Char(c));
+        }
+    }
+
+    #[test]
+    fn typed_task_is_sent_before_proceeding() {
+        let (mut ui, receiver, output) = ui_with_output();
+        ui.ask_for_ack(PomodoroStates::Pomodoro, 0);
+        type_keys(&mut ui, "wrx");
+        ui.handle_key(Key::Backspace);
+        type_keys(&mut ui, "ite");
+        ui.handle_key(Key::Enter);
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(UIChannel::Task(Some(ref task))) if task == "write"
+        ));
+        assert!(matches!(receiver.try_recv(), Ok(UIChannel::Proceed)));
+        assert_eq!(ui.mode, Mode::Running);
+        assert_eq!(output.prompts.lock().unwrap().last().unwrap(), "write");
+    }
+
+    #[test]
+    fn empty_answer_keeps_the_task_and_dash_clears_it() {
+        let (mut ui, receiver, _output) = ui_with_output();
+        ui.set_task(Some("read".to_string()));
+        ui.ask_for_ack(PomodoroStates::Pomodoro, 0);
+        ui.handle_key(Key::Enter);
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(UIChannel::Task(Some(ref task))) if task == "read"
+        ));
+        assert!(matches!(receiver.try_recv(), Ok(UIChannel::Proceed)));
+        ui.ask_for_ack(PomodoroStates::Pomodoro, 0);
+        type_keys(&mut ui, "-");
+        ui.handle_key(Key::Enter);
+        assert!(matches!(receiver.try_recv(), Ok(UIChannel::Task(None))));
+    }
+
+    #[test]
+    fn space_pauses_and_resumes_a_running_interval() {
+        let (mut ui, receiver, _output) = ui_with_output();
+        ui.handle_message(PomodoroChannel::Update(progress(PomodoroStates::Pomodoro)));
+        ui.handle_key(Key::Char(' '));
+        assert!(matches!(receiver.try_recv(), Ok(UIChannel::Pause)));
+        ui.handle_message(PomodoroChannel::Paused(progress(PomodoroStates::Pomodoro)));
+        ui.handle_key(Key::Char(' '));
+        assert!(matches!(receiver.try_recv(), Ok(UIChannel::Resume)));
+        ui.handle_key(Key::Char('s'));
+        assert!(matches!(receiver.try_recv(), Ok(UIChannel::Skip)));
+        ui.handle_key(Key::Char('a'));
+        assert!(matches!(receiver.try_recv(), Ok(UIChannel::Abort)));
+    }
+
+    #[test]
+    fn break_starts_on_enter() {
+        let (mut ui, receiver, _output) = ui_with_output();
+        ui.handle_message(PomodoroChannel::Completed(
+            PomodoroStates::ShortBreak,
+            1,
+            None,
+        ));
+        ui.handle_key(Key::Char(' '));
+        assert!(receiver.try_recv().is_err());
+        ui.handle_key(Key::Enter);
+        assert!(matches!(receiver.try_recv(), Ok(UIChannel::Proceed)));
+    }
+
+    #[test]
+    fn quit_keys_cancel_in_every_mode() {
+        for key in [Key::Char('q'), Key::Esc, Key::Interrupt] {
+            let (mut ui, receiver, _output) = ui_with_output();
+            ui.handle_message(PomodoroChannel::Update(progress(PomodoroStates::Pomodoro)));
+            ui.handle_key(key);
+            assert!(matches!(receiver.try_recv(), Ok(UIChannel::Cancel)));
+            assert_eq!(ui.mode, Mode::Quit);
+        }
+        let (mut ui, receiver, _output) = ui_with_output();
+        ui.ask_for_ack(PomodoroStates::Pomodoro, 0);
+        ui.handle_key(Key::Esc);
+        assert!(matches!(receiver.try_recv(), Ok(UIChannel::Cancel)));
+    }
+
+    #[test]
+    fn interrupter_quits_like_ctrl_c() {
+        let (mut ui, receiver, _output) = ui_with_output();
+        ui.handle_message(PomodoroChannel::Update(progress(PomodoroStates::Pomodoro)));
+        ui.interrupter()();
+        match ui.events.try_recv() {
+            Ok(UIEvent::Key(key)) => ui.handle_key(key),
+            _ => panic!("no key event"),
+        }
+        assert!(matches!(receiver.try_recv(), Ok(UIChannel::Cancel)));
+        assert_eq!(ui.mode, Mode::Quit);
+    }
+
+    #[test]
+    fn q_is_part_of_a_task_name() {
+        let (mut ui, receiver, _output) = ui_with_output();
+        ui.ask_for_ack(PomodoroStates::Pomodoro, 0);
+        type_keys(&mut ui, "q");
+        assert_eq!(ui.mode, Mode::Waiting(PomodoroStates::Pomodoro));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn a_new_day_redraws_the_waiting_prompt() {
+        let (mut ui, _receiver, output) = ui_with_output();
+        ui.ask_for_ack(PomodoroStates::Pomodoro, 5);
+        type_keys(&mut ui, "wr");
+        ui.handle_message(PomodoroChannel::DayStarted(0));
+        assert_eq!(
+            output.summaries.lock().unwrap().last().unwrap(),
+            "You have finished 0 pomodoros today."
+        );
+        assert_eq!(output.prompts.lock().unwrap().last().unwrap(), "wr");
+    }
+
+    #[test]
+    fn summary_shows_progress_towards_the_goal() {
+        assert_eq!(
+            finished_pomodoro_str(6, None),
+            "You have finished 6 pomodoros today."
+        );
+        assert_eq!(
+            finished_pomodoro_str(6, Some(10)),
+            "You have finished 6/10 pomodoros today."
+        );
+        assert_eq!(
+            finished_pomodoro_str(10, Some(10)),
+            "You have finished 10 pomodoros today, your goal of 10 is reached."
+        );
     }
 }